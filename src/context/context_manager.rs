@@ -1,6 +1,6 @@
 // src/context/context_manager.rs
 
-use super::config::{Config, BlockchainConfig, WalletConfig, ChainType, NetworkType};
+use super::config::{Config, ChainType, NetworkType};
 use std::collections::HashMap;
 
 pub struct ContextManager {
@@ -8,6 +8,12 @@ pub struct ContextManager {
     active_config: String,
 }
 
+impl Default for ContextManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ContextManager {
     pub fn new() -> Self {
         Self {
@@ -1,37 +1,90 @@
 use ecies::{
-    decrypt, 
-    encrypt, 
+    decrypt,
+    encrypt,
     utils::generate_keypair,
     PublicKey,
     SecretKey
 };
+use zeroize::Zeroizing;
 
 use std::fs::{File};
 use std::io::{Read, Write};
 
 use crate::utils::read_hex_from_file;
 
+use super::error::CryptoError;
+use super::stream::{decrypt_stream, encrypt_stream, key_id, DEFAULT_CHUNK_SIZE};
+
+/// Size of the ephemeral public key, nonce and tag ECIES adds to a payload.
+const ECIES_OVERHEAD: usize = 65 + 16 + 16;
+
 /// Generate New PrivateKey and PublicKey
 pub fn new_keypair() -> (SecretKey, PublicKey) {
     generate_keypair()
 }
 
+/// A short, non-secret fingerprint of a public key that is safe to log.
+pub fn key_fingerprint(public_key: &PublicKey) -> String {
+    hex::encode(key_id(public_key))
+}
+
+/// Parse a hex encoded secp256k1 public key (compressed or uncompressed).
+pub fn parse_public_key(hex_string: &str) -> Result<PublicKey, CryptoError> {
+    let bytes = hex::decode(hex_string.trim().trim_start_matches("0x"))
+        .map_err(|e| CryptoError::InvalidKey(format!("public key is not hex: {}", e)))?;
+    PublicKey::parse_slice(&bytes, None).map_err(|e| CryptoError::InvalidKey(format!("invalid public key: {:?}", e)))
+}
+
+/// Parse a hex encoded secp256k1 secret key. Intermediate buffers are zeroized.
+pub fn parse_secret_key(hex_string: &str) -> Result<SecretKey, CryptoError> {
+    let bytes = Zeroizing::new(
+        hex::decode(hex_string.trim().trim_start_matches("0x"))
+            .map_err(|_| CryptoError::InvalidKey("secret key is not hex".to_string()))?,
+    );
+    SecretKey::parse_slice(&bytes).map_err(|_| CryptoError::InvalidKey("invalid secret key".to_string()))
+}
+
+/// Read a hex encoded public key from file
+pub fn load_public_key(public_key_path: &str) -> Result<PublicKey, CryptoError> {
+    parse_public_key(&read_hex_from_file(public_key_path)?)
+}
+
+/// Read a hex encoded secret key from file
+pub fn load_secret_key(secret_key_path: &str) -> Result<SecretKey, CryptoError> {
+    let hex_string = Zeroizing::new(read_hex_from_file(secret_key_path)?);
+    parse_secret_key(&hex_string)
+}
+
+/// Encrypt bytes with Public Key
+pub fn encrypt_bytes(public_key: &PublicKey, data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    encrypt(&public_key.serialize(), data).map_err(|e| CryptoError::Encryption(format!("{:?}", e)))
+}
+
+/// Decrypt bytes with Secret Key. The plaintext is zeroized when dropped.
+pub fn decrypt_bytes(secret_key: &SecretKey, data: &[u8]) -> Result<Zeroizing<Vec<u8>>, CryptoError> {
+    if data.len() < ECIES_OVERHEAD {
+        return Err(CryptoError::Corrupted("ciphertext is too short".to_string()));
+    }
+    if PublicKey::parse_slice(&data[..65], None).is_err() {
+        return Err(CryptoError::Corrupted("invalid ephemeral key".to_string()));
+    }
+    let secret_key_bytes = Zeroizing::new(secret_key.serialize());
+    decrypt(&*secret_key_bytes, data)
+        .map(Zeroizing::new)
+        .map_err(|_| CryptoError::AuthenticationFailed)
+}
+
 /// Encrypt File with Public Key
-pub fn encrypt_file(file_path: &str, public_key_path: &str, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Encrypting file: {}", file_path);
-    
+pub fn encrypt_file_with_key(file_path: &str, public_key: &PublicKey, output_path: &str) -> Result<(), CryptoError> {
+    log::debug!("encrypting {} to key {}", file_path, key_fingerprint(public_key));
+
     // Read the binary file
     let mut file = File::open(file_path)?;
-    let mut file_data = Vec::new();
-    file.read_to_end(&mut file_data)?; 
-    
-    // Read the hex string from the file
-    let hex_string = read_hex_from_file(public_key_path)?;
-    println!("Public key read from file: {}", hex_string);
-    let public_key_bytes = hex::decode(&hex_string).expect("Failed to decode public key");
+    let mut file_data = Zeroizing::new(Vec::new());
+    file.read_to_end(&mut file_data)?;
 
     // Encrypt the data
-    let encrypted_data = encrypt(&public_key_bytes, &file_data).unwrap();
+    let encrypted_data = encrypt_bytes(public_key, &file_data)?;
 
     // Write the encrypted data to a file
     let mut output = File::create(output_path)?;
@@ -40,22 +93,17 @@ pub fn encrypt_file(file_path: &str, public_key_path: &str, output_path: &str) -
     Ok(())
 }
 
-/// Decrypt File with Private Key
-pub fn decrypt_file(file_path: &str, secret_key_path: &str, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Decrypting file: {}", file_path);
+/// Decrypt File with Secret Key
+pub fn decrypt_file_with_key(file_path: &str, secret_key: &SecretKey, output_path: &str) -> Result<(), CryptoError> {
+    log::debug!("decrypting {} with key {}", file_path, key_fingerprint(&PublicKey::from_secret_key(secret_key)));
 
     // Read the binary file
     let mut file = File::open(file_path)?;
     let mut file_data = Vec::new();
     file.read_to_end(&mut file_data)?;
 
-    // Read the hex string from the file
-    let hex_string = read_hex_from_file(secret_key_path)?;
-    println!("Secret key read from file: {}", hex_string);
-    let secret_key_bytes = hex::decode(&hex_string).expect("Failed to decode secret key");
-
     // Decrypt the data
-    let decrypted_data = decrypt(&secret_key_bytes, &file_data).unwrap();
+    let decrypted_data = decrypt_bytes(secret_key, &file_data)?;
 
     // Write the decrypted data to a file
     let mut decrypted_output = File::create(output_path)?;
@@ -64,3 +112,32 @@ pub fn decrypt_file(file_path: &str, secret_key_path: &str, output_path: &str) -
     Ok(())
 }
 
+/// Encrypt File with Public Key read from file
+pub fn encrypt_file(file_path: &str, public_key_path: &str, output_path: &str) -> Result<(), CryptoError> {
+    encrypt_file_with_key(file_path, &load_public_key(public_key_path)?, output_path)
+}
+
+/// Decrypt File with Private Key read from file
+pub fn decrypt_file(file_path: &str, secret_key_path: &str, output_path: &str) -> Result<(), CryptoError> {
+    decrypt_file_with_key(file_path, &load_secret_key(secret_key_path)?, output_path)
+}
+
+/// Encrypt File with Public Key, streaming it in authenticated chunks
+pub async fn encrypt_file_stream(file_path: &str, public_key_path: &str, output_path: &str) -> Result<u64, CryptoError> {
+    let public_key = load_public_key(public_key_path)?;
+    log::debug!("stream encrypting {} to key {}", file_path, key_fingerprint(&public_key));
+
+    let mut input = tokio::io::BufReader::new(tokio::fs::File::open(file_path).await?);
+    let mut output = tokio::io::BufWriter::new(tokio::fs::File::create(output_path).await?);
+    encrypt_stream(&mut input, &mut output, &public_key, DEFAULT_CHUNK_SIZE).await
+}
+
+/// Decrypt File encrypted with `encrypt_file_stream` using Private Key
+pub async fn decrypt_file_stream(file_path: &str, secret_key_path: &str, output_path: &str) -> Result<u64, CryptoError> {
+    let secret_key = load_secret_key(secret_key_path)?;
+    log::debug!("stream decrypting {}", file_path);
+
+    let mut input = tokio::io::BufReader::new(tokio::fs::File::open(file_path).await?);
+    let mut output = tokio::io::BufWriter::new(tokio::fs::File::create(output_path).await?);
+    decrypt_stream(&mut input, &mut output, &secret_key).await
+}
@@ -0,0 +1,309 @@
+// src/crypto/envelope.rs
+
+//! Multi-recipient envelope encryption.
+//!
+//! The payload is sealed once with a random AES-256-GCM data key and the data
+//! key is wrapped with ECIES to every recipient. Recipients can be added or
+//! removed by rewriting the key table alone; the body is never re-encrypted.
+//! Layout:
+//!
+//! ```text
+//! "SWTCHENV" | version u8 | count u16 | count x (key_id [8] | wrapped_len u16 | wrapped_key) | nonce [12] | ciphertext
+//! ```
+//!
+//! Only the magic and version are bound in as associated data, since the key
+//! table is expected to change. Removing a recipient stops new copies of the
+//! envelope from being opened with that key but cannot take back a data key
+//! the recipient has already unwrapped; re-seal the payload for that.
+
+use std::collections::HashMap;
+use std::fs;
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use async_trait::async_trait;
+use ecies::{PublicKey, SecretKey};
+use ethers::types::Address;
+use zeroize::Zeroizing;
+
+use super::ec::key_fingerprint;
+use super::error::CryptoError;
+use super::stream::key_id;
+
+pub const ENVELOPE_MAGIC: &[u8; 8] = b"SWTCHENV";
+pub const ENVELOPE_VERSION: u8 = 1;
+
+const NONCE_SIZE: usize = 12;
+
+/// Resolves a DID to the secp256k1 public key its owner encrypts to.
+#[async_trait]
+pub trait KeyResolver: Send + Sync {
+    async fn resolve_key(&self, did: Address) -> Result<PublicKey, CryptoError>;
+}
+
+/// A `KeyResolver` backed by a fixed map, for keys distributed out of band.
+#[derive(Debug, Clone, Default)]
+pub struct StaticKeyResolver {
+    keys: HashMap<Address, PublicKey>,
+}
+
+impl StaticKeyResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_key(mut self, did: Address, public_key: PublicKey) -> Self {
+        self.keys.insert(did, public_key);
+        self
+    }
+}
+
+#[async_trait]
+impl KeyResolver for StaticKeyResolver {
+    async fn resolve_key(&self, did: Address) -> Result<PublicKey, CryptoError> {
+        self.keys
+            .get(&did)
+            .copied()
+            .ok_or_else(|| CryptoError::InvalidKey(format!("no public key known for {:?}", did)))
+    }
+}
+
+/// Someone an envelope is encrypted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recipient {
+    Key(PublicKey),
+    /// A DID, resolved to a key with a `KeyResolver` when sealing.
+    Did(Address),
+}
+
+impl From<PublicKey> for Recipient {
+    fn from(public_key: PublicKey) -> Self {
+        Recipient::Key(public_key)
+    }
+}
+
+impl From<Address> for Recipient {
+    fn from(did: Address) -> Self {
+        Recipient::Did(did)
+    }
+}
+
+/// Resolve a mixed list of recipients to public keys.
+pub async fn resolve_recipients(
+    recipients: &[Recipient],
+    resolver: &dyn KeyResolver,
+) -> Result<Vec<PublicKey>, CryptoError> {
+    let mut keys = Vec::with_capacity(recipients.len());
+    for recipient in recipients {
+        keys.push(match recipient {
+            Recipient::Key(public_key) => *public_key,
+            Recipient::Did(did) => resolver.resolve_key(*did).await?,
+        });
+    }
+    Ok(keys)
+}
+
+/// The data key wrapped to one recipient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrappedKey {
+    pub key_id: [u8; 8],
+    pub wrapped: Vec<u8>,
+}
+
+/// A payload encrypted once for any number of recipients.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    recipients: Vec<WrappedKey>,
+    nonce: [u8; NONCE_SIZE],
+    ciphertext: Vec<u8>,
+}
+
+fn wrap_key(data_key: &[u8], public_key: &PublicKey) -> Result<WrappedKey, CryptoError> {
+    let wrapped = ecies::encrypt(&public_key.serialize(), data_key)
+        .map_err(|e| CryptoError::Encryption(e.to_string()))?;
+    Ok(WrappedKey { key_id: key_id(public_key), wrapped })
+}
+
+fn body_aad() -> [u8; 9] {
+    let mut aad = [0u8; 9];
+    aad[..8].copy_from_slice(ENVELOPE_MAGIC);
+    aad[8] = ENVELOPE_VERSION;
+    aad
+}
+
+impl Envelope {
+    /// Encrypt `plaintext` to every key in `recipients`. Duplicate keys are
+    /// wrapped once.
+    pub fn seal(plaintext: &[u8], recipients: &[PublicKey]) -> Result<Self, CryptoError> {
+        if recipients.is_empty() {
+            return Err(CryptoError::Encryption("envelope needs at least one recipient".to_string()));
+        }
+
+        let mut data_key = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(data_key.as_mut());
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(data_key.as_ref()));
+        let nonce = Aes256Gcm::generate_nonce(OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: plaintext, aad: &body_aad() })
+            .map_err(|e| CryptoError::Encryption(e.to_string()))?;
+
+        let mut envelope = Self { recipients: Vec::new(), nonce: nonce.into(), ciphertext };
+        for public_key in recipients {
+            if !envelope.is_recipient(public_key) {
+                envelope.recipients.push(wrap_key(data_key.as_ref(), public_key)?);
+            }
+        }
+        log::debug!("sealed envelope for {} recipient(s)", envelope.recipients.len());
+        Ok(envelope)
+    }
+
+    /// Encrypt `plaintext` to public keys and DIDs alike.
+    pub async fn seal_for(
+        plaintext: &[u8],
+        recipients: &[Recipient],
+        resolver: &dyn KeyResolver,
+    ) -> Result<Self, CryptoError> {
+        Self::seal(plaintext, &resolve_recipients(recipients, resolver).await?)
+    }
+
+    /// Key ids of every recipient, in the order they were added.
+    pub fn recipients(&self) -> Vec<[u8; 8]> {
+        self.recipients.iter().map(|r| r.key_id).collect()
+    }
+
+    pub fn is_recipient(&self, public_key: &PublicKey) -> bool {
+        let id = key_id(public_key);
+        self.recipients.iter().any(|r| r.key_id == id)
+    }
+
+    /// Unwrap the data key for `secret_key`, failing with `WrongKey` if the
+    /// envelope wasn't encrypted to it.
+    fn data_key(&self, secret_key: &SecretKey) -> Result<Zeroizing<Vec<u8>>, CryptoError> {
+        let id = key_id(&PublicKey::from_secret_key(secret_key));
+        let entry = self.recipients.iter().find(|r| r.key_id == id).ok_or(CryptoError::WrongKey)?;
+        let secret_key_bytes = Zeroizing::new(secret_key.serialize());
+        let data_key = Zeroizing::new(
+            ecies::decrypt(secret_key_bytes.as_ref(), &entry.wrapped).map_err(|_| CryptoError::AuthenticationFailed)?,
+        );
+        if data_key.len() != 32 {
+            return Err(CryptoError::Corrupted("data key has the wrong length".to_string()));
+        }
+        Ok(data_key)
+    }
+
+    /// Decrypt the payload with one recipient's secret key.
+    pub fn open(&self, secret_key: &SecretKey) -> Result<Zeroizing<Vec<u8>>, CryptoError> {
+        let data_key = self.data_key(secret_key)?;
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&data_key));
+        cipher
+            .decrypt(Nonce::from_slice(&self.nonce), Payload { msg: &self.ciphertext, aad: &body_aad() })
+            .map(Zeroizing::new)
+            .map_err(|_| CryptoError::AuthenticationFailed)
+    }
+
+    /// Give `public_key` access. `secret_key` must belong to an existing
+    /// recipient so the data key can be unwrapped. Returns `false` if the key
+    /// was already a recipient.
+    pub fn add_recipient(&mut self, secret_key: &SecretKey, public_key: &PublicKey) -> Result<bool, CryptoError> {
+        if self.is_recipient(public_key) {
+            return Ok(false);
+        }
+        let data_key = self.data_key(secret_key)?;
+        self.recipients.push(wrap_key(&data_key, public_key)?);
+        log::debug!("added envelope recipient {}", key_fingerprint(public_key));
+        Ok(true)
+    }
+
+    /// Drop `public_key`'s wrapped key. Returns `false` if it wasn't a
+    /// recipient. The last recipient can't be removed.
+    pub fn remove_recipient(&mut self, public_key: &PublicKey) -> Result<bool, CryptoError> {
+        let id = key_id(public_key);
+        let Some(position) = self.recipients.iter().position(|r| r.key_id == id) else {
+            return Ok(false);
+        };
+        if self.recipients.len() == 1 {
+            return Err(CryptoError::Encryption("cannot remove the last envelope recipient".to_string()));
+        }
+        self.recipients.remove(position);
+        log::debug!("removed envelope recipient {}", key_fingerprint(public_key));
+        Ok(true)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(ENVELOPE_MAGIC);
+        out.push(ENVELOPE_VERSION);
+        out.extend_from_slice(&(self.recipients.len() as u16).to_be_bytes());
+        for recipient in &self.recipients {
+            out.extend_from_slice(&recipient.key_id);
+            out.extend_from_slice(&(recipient.wrapped.len() as u16).to_be_bytes());
+            out.extend_from_slice(&recipient.wrapped);
+        }
+        out.extend_from_slice(&self.nonce);
+        out.extend_from_slice(&self.ciphertext);
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, CryptoError> {
+        let mut reader = SliceReader { data, offset: 0 };
+        if reader.take(8)? != ENVELOPE_MAGIC {
+            return Err(CryptoError::InvalidHeader("missing envelope magic".to_string()));
+        }
+        let version = reader.take(1)?[0];
+        if version != ENVELOPE_VERSION {
+            return Err(CryptoError::UnsupportedVersion(version));
+        }
+        let count = reader.take_u16()?;
+        if count == 0 {
+            return Err(CryptoError::InvalidHeader("envelope has no recipients".to_string()));
+        }
+        let mut recipients = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let mut key_id = [0u8; 8];
+            key_id.copy_from_slice(reader.take(8)?);
+            let wrapped_len = reader.take_u16()? as usize;
+            recipients.push(WrappedKey { key_id, wrapped: reader.take(wrapped_len)?.to_vec() });
+        }
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce.copy_from_slice(reader.take(NONCE_SIZE)?);
+        Ok(Self { recipients, nonce, ciphertext: reader.rest().to_vec() })
+    }
+}
+
+struct SliceReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> SliceReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CryptoError> {
+        let end = self.offset.checked_add(len).filter(|end| *end <= self.data.len()).ok_or(CryptoError::Truncated)?;
+        let slice = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn take_u16(&mut self) -> Result<u16, CryptoError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.offset..]
+    }
+}
+
+/// Encrypt a file to several recipients.
+pub fn seal_file(file_path: &str, recipients: &[PublicKey], output_path: &str) -> Result<(), CryptoError> {
+    let plaintext = Zeroizing::new(fs::read(file_path)?);
+    fs::write(output_path, Envelope::seal(&plaintext, recipients)?.to_bytes())?;
+    Ok(())
+}
+
+/// Decrypt a file sealed with `seal_file` using any recipient's secret key.
+pub fn open_file(file_path: &str, secret_key: &SecretKey, output_path: &str) -> Result<(), CryptoError> {
+    let envelope = Envelope::from_bytes(&fs::read(file_path)?)?;
+    fs::write(output_path, envelope.open(secret_key)?)?;
+    Ok(())
+}
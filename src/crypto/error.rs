@@ -0,0 +1,73 @@
+// src/crypto/error.rs
+
+use ethers::types::Address;
+use std::fmt;
+
+/// Errors returned by the encryption APIs in `crypto`.
+#[derive(Debug)]
+pub enum CryptoError {
+    Io(std::io::Error),
+    /// A key could not be parsed.
+    InvalidKey(String),
+    /// Encrypting or wrapping a key failed.
+    Encryption(String),
+    /// The ciphertext header is malformed.
+    InvalidHeader(String),
+    UnsupportedVersion(u8),
+    /// The ciphertext is structurally invalid.
+    Corrupted(String),
+    /// The ciphertext failed authentication. With single-blob ECIES this
+    /// means either the wrong key was used or the ciphertext was modified.
+    AuthenticationFailed,
+    /// The ciphertext was encrypted to a different key.
+    WrongKey,
+    /// A chunk failed authentication: it was corrupted, reordered or spliced
+    /// in from another stream.
+    ChunkAuthentication { index: u32 },
+    /// The stream ended before its final chunk.
+    Truncated,
+    /// Data follows the final chunk.
+    TrailingData,
+    /// A signature is malformed or doesn't match the signed content.
+    InvalidSignature(String),
+    /// The signature is valid but the signer may not act for the DID.
+    UnauthorizedSigner { did: Address, signer: Address },
+    /// Looking up the DID's owner or delegates failed.
+    Identity(String),
+    /// The signature was made for a different chain.
+    ChainMismatch { expected: u64, found: u64 },
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::Io(e) => write!(f, "io error: {}", e),
+            CryptoError::InvalidKey(msg) => write!(f, "invalid key: {}", msg),
+            CryptoError::Encryption(msg) => write!(f, "encryption failed: {}", msg),
+            CryptoError::InvalidHeader(msg) => write!(f, "invalid ciphertext header: {}", msg),
+            CryptoError::UnsupportedVersion(v) => write!(f, "unsupported ciphertext version {}", v),
+            CryptoError::Corrupted(msg) => write!(f, "corrupted ciphertext: {}", msg),
+            CryptoError::AuthenticationFailed => write!(f, "wrong key or corrupted ciphertext"),
+            CryptoError::WrongKey => write!(f, "ciphertext was not encrypted to this key"),
+            CryptoError::ChunkAuthentication { index } => write!(f, "chunk {} failed authentication", index),
+            CryptoError::Truncated => write!(f, "ciphertext is truncated"),
+            CryptoError::TrailingData => write!(f, "unexpected data after final chunk"),
+            CryptoError::InvalidSignature(msg) => write!(f, "invalid signature: {}", msg),
+            CryptoError::UnauthorizedSigner { did, signer } => {
+                write!(f, "{:?} is not the owner or a delegate of {:?}", signer, did)
+            }
+            CryptoError::Identity(msg) => write!(f, "identity lookup failed: {}", msg),
+            CryptoError::ChainMismatch { expected, found } => {
+                write!(f, "signature is for chain {}, expected chain {}", found, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+impl From<std::io::Error> for CryptoError {
+    fn from(e: std::io::Error) -> Self {
+        CryptoError::Io(e)
+    }
+}
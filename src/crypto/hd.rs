@@ -0,0 +1,135 @@
+// src/crypto/hd.rs
+
+//! Hierarchical deterministic keys (BIP-32/39/44), so that one backed-up
+//! mnemonic regenerates every SWTCH key a user holds.
+//!
+//! Each identity gets an index, and each of its sub-spaces an index starting
+//! at 1; sub-space 0 is the identity itself. Keys are derived along:
+//!
+//! ```text
+//! signing:    m/44'/60'/{identity}'/0/{sub_space}
+//! encryption: m/7946'/60'/{identity}'/{sub_space}'
+//! ```
+//!
+//! Signing keys follow BIP-44 for Ethereum with one account per identity, so
+//! they can be imported into ordinary wallets; the identity's DID is the
+//! address of its signing key. Encryption keys live under a separate purpose
+//! and are fully hardened, so they never coincide with a wallet account and
+//! a leaked extended public key reveals nothing about them.
+
+use ethers::core::k256::ecdsa::SigningKey;
+use ethers::core::rand::thread_rng;
+use ethers::signers::coins_bip39::{English, Mnemonic};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::Address;
+use zeroize::Zeroizing;
+
+use super::error::CryptoError;
+use super::keys::KeyPair;
+
+/// BIP-43 purpose of the encryption key tree.
+pub const ENCRYPTION_PURPOSE: u32 = 7946;
+
+/// Path of the signing key for an identity or one of its sub-spaces.
+pub fn signing_path(identity: u32, sub_space: u32) -> String {
+    format!("m/44'/60'/{}'/0/{}", identity, sub_space)
+}
+
+/// Path of the encryption key for an identity or one of its sub-spaces.
+pub fn encryption_path(identity: u32, sub_space: u32) -> String {
+    format!("m/{}'/60'/{}'/{}'", ENCRYPTION_PURPOSE, identity, sub_space)
+}
+
+/// The keys derived for one identity or sub-space.
+#[derive(Debug, Clone)]
+pub struct IdentityKeys {
+    pub identity: u32,
+    pub sub_space: u32,
+    pub signing: LocalWallet,
+    pub encryption: KeyPair,
+}
+
+impl IdentityKeys {
+    /// The DID, i.e. the address of the signing key.
+    pub fn did(&self) -> Address {
+        self.signing.address()
+    }
+}
+
+/// Derives SWTCH keys from a BIP-39 mnemonic.
+pub struct HdKeyring {
+    mnemonic: Mnemonic<English>,
+    password: Option<Zeroizing<String>>,
+}
+
+impl HdKeyring {
+    /// Generate a new mnemonic of 12, 15, 18, 21 or 24 words.
+    pub fn generate(word_count: usize) -> Result<Self, CryptoError> {
+        let mnemonic = Mnemonic::<English>::new_with_count(&mut thread_rng(), word_count)
+            .map_err(|e| CryptoError::InvalidKey(e.to_string()))?;
+        Ok(Self { mnemonic, password: None })
+    }
+
+    /// Restore from an English mnemonic phrase.
+    pub fn from_phrase(phrase: &str) -> Result<Self, CryptoError> {
+        let phrase = Zeroizing::new(phrase.split_whitespace().collect::<Vec<_>>().join(" "));
+        let mnemonic =
+            Mnemonic::<English>::new_from_phrase(&phrase).map_err(|_| CryptoError::InvalidKey("invalid mnemonic".to_string()))?;
+        Ok(Self { mnemonic, password: None })
+    }
+
+    /// Use a BIP-39 passphrase. A different passphrase yields unrelated keys.
+    pub fn with_password(mut self, password: &str) -> Self {
+        self.password = Some(Zeroizing::new(password.to_string()));
+        self
+    }
+
+    /// The mnemonic phrase, for backup.
+    pub fn phrase(&self) -> Zeroizing<String> {
+        Zeroizing::new(self.mnemonic.to_phrase())
+    }
+
+    fn derive(&self, path: &str) -> Result<SigningKey, CryptoError> {
+        let xpriv = self
+            .mnemonic
+            .derive_key(path, self.password.as_ref().map(|p| p.as_str()))
+            .map_err(|e| CryptoError::InvalidKey(format!("derivation of {} failed: {}", path, e)))?;
+        let key: &SigningKey = xpriv.as_ref();
+        Ok(key.clone())
+    }
+
+    pub fn signing_key(&self, identity: u32, sub_space: u32) -> Result<LocalWallet, CryptoError> {
+        Ok(LocalWallet::from(self.derive(&signing_path(identity, sub_space))?))
+    }
+
+    pub fn encryption_key(&self, identity: u32, sub_space: u32) -> Result<KeyPair, CryptoError> {
+        let wallet = LocalWallet::from(self.derive(&encryption_path(identity, sub_space))?);
+        KeyPair::from_wallet(&wallet)
+    }
+
+    /// Both keys of an identity's own space.
+    pub fn identity(&self, identity: u32) -> Result<IdentityKeys, CryptoError> {
+        self.sub_space(identity, 0)
+    }
+
+    /// Both keys of one of an identity's sub-spaces.
+    pub fn sub_space(&self, identity: u32, sub_space: u32) -> Result<IdentityKeys, CryptoError> {
+        Ok(IdentityKeys {
+            identity,
+            sub_space,
+            signing: self.signing_key(identity, sub_space)?,
+            encryption: self.encryption_key(identity, sub_space)?,
+        })
+    }
+
+    /// Find which of the first `max_identities` identities has `did`, e.g.
+    /// after restoring from a mnemonic.
+    pub fn find_identity(&self, did: Address, max_identities: u32) -> Result<Option<IdentityKeys>, CryptoError> {
+        for identity in 0..max_identities {
+            if self.signing_key(identity, 0)?.address() == did {
+                return self.identity(identity).map(Some);
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,367 @@
+// src/crypto/keys.rs
+
+//! secp256k1 key pairs that can be stored in the common key file formats and
+//! used both to decrypt (ECIES) and to sign (as an ethers `LocalWallet`).
+//!
+//! Secret key files are always written with 0600 permissions. Password
+//! protected keys use the Ethereum V3 keystore JSON format, so they can be
+//! opened by other Ethereum tooling.
+
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use ecies::{PublicKey, SecretKey};
+use ethers::core::rand::{thread_rng, Rng};
+use ethers::signers::LocalWallet;
+use ethers::types::Address;
+use ethers::utils::keccak256;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::utils::write_private_file;
+
+use super::ec::{key_fingerprint, parse_public_key, parse_secret_key};
+use super::error::CryptoError;
+
+const SEC1_LABEL: &str = "EC PRIVATE KEY";
+const PKCS8_LABEL: &str = "PRIVATE KEY";
+const SPKI_LABEL: &str = "PUBLIC KEY";
+
+/// Text formats a key can be stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyFormat {
+    Hex,
+    /// SEC1 `EC PRIVATE KEY` PEM. Public keys are written as SPKI `PUBLIC KEY`.
+    Sec1Pem,
+    /// PKCS#8 `PRIVATE KEY` PEM. Public keys are written as SPKI `PUBLIC KEY`.
+    Pkcs8Pem,
+    /// JSON Web Key with `crv` `secp256k1`.
+    Jwk,
+}
+
+/// A secp256k1 JSON Web Key. `d` is only present for secret keys.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Jwk {
+    kty: String,
+    crv: String,
+    x: String,
+    y: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    d: Option<String>,
+}
+
+/// The Ethereum address controlled by a public key.
+pub fn public_key_to_address(public_key: &PublicKey) -> Address {
+    Address::from_slice(&keccak256(&public_key.serialize()[1..])[12..])
+}
+
+fn to_k256_secret(secret_key: &SecretKey) -> Result<k256::SecretKey, CryptoError> {
+    k256::SecretKey::from_slice(Zeroizing::new(secret_key.serialize()).as_ref())
+        .map_err(|_| CryptoError::InvalidKey("invalid secret key".to_string()))
+}
+
+fn from_k256_secret(secret_key: &k256::SecretKey) -> Result<SecretKey, CryptoError> {
+    SecretKey::parse_slice(&secret_key.to_bytes()).map_err(|_| CryptoError::InvalidKey("invalid secret key".to_string()))
+}
+
+fn from_k256_public(public_key: &k256::PublicKey) -> Result<PublicKey, CryptoError> {
+    PublicKey::parse_slice(public_key.to_encoded_point(false).as_bytes(), None)
+        .map_err(|e| CryptoError::InvalidKey(format!("invalid public key: {:?}", e)))
+}
+
+fn pem_encode(label: &str, der: &[u8]) -> Zeroizing<String> {
+    let body = Zeroizing::new(STANDARD.encode(der));
+    let mut pem = Zeroizing::new(format!("-----BEGIN {}-----\n", label));
+    for line in body.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).unwrap_or_default());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+/// Split a PEM document into its label and DER body.
+fn pem_decode(pem: &str) -> Result<(String, Zeroizing<Vec<u8>>), CryptoError> {
+    let pem = pem.trim();
+    let label = pem
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("-----BEGIN "))
+        .and_then(|line| line.strip_suffix("-----"))
+        .ok_or_else(|| CryptoError::InvalidKey("missing PEM header".to_string()))?
+        .to_string();
+    if !pem.ends_with(&format!("-----END {}-----", label)) {
+        return Err(CryptoError::InvalidKey("missing PEM footer".to_string()));
+    }
+    let body: Zeroizing<String> = Zeroizing::new(
+        pem.lines()
+            .filter(|line| !line.starts_with("-----"))
+            .map(str::trim)
+            .collect(),
+    );
+    let der = STANDARD
+        .decode(body.as_bytes())
+        .map_err(|_| CryptoError::InvalidKey("PEM body is not base64".to_string()))?;
+    Ok((label, Zeroizing::new(der)))
+}
+
+fn jwk_coordinate(value: &str) -> Result<Zeroizing<Vec<u8>>, CryptoError> {
+    let bytes = Zeroizing::new(
+        URL_SAFE_NO_PAD
+            .decode(value)
+            .map_err(|_| CryptoError::InvalidKey("JWK value is not base64url".to_string()))?,
+    );
+    if bytes.len() != 32 {
+        return Err(CryptoError::InvalidKey("JWK value has the wrong length".to_string()));
+    }
+    Ok(bytes)
+}
+
+fn parse_jwk(json: &str) -> Result<Jwk, CryptoError> {
+    let jwk: Jwk = serde_json::from_str(json).map_err(|e| CryptoError::InvalidKey(format!("invalid JWK: {}", e)))?;
+    if jwk.kty != "EC" || jwk.crv != "secp256k1" {
+        return Err(CryptoError::InvalidKey(format!("unsupported JWK {} {}", jwk.kty, jwk.crv)));
+    }
+    Ok(jwk)
+}
+
+fn jwk_public_key(jwk: &Jwk) -> Result<PublicKey, CryptoError> {
+    let mut point = Vec::with_capacity(65);
+    point.push(0x04);
+    point.extend_from_slice(&jwk_coordinate(&jwk.x)?);
+    point.extend_from_slice(&jwk_coordinate(&jwk.y)?);
+    PublicKey::parse_slice(&point, None).map_err(|e| CryptoError::InvalidKey(format!("invalid JWK point: {:?}", e)))
+}
+
+/// Encode a public key. `Sec1Pem` and `Pkcs8Pem` both produce SPKI PEM.
+pub fn encode_public_key(public_key: &PublicKey, format: KeyFormat) -> Result<String, CryptoError> {
+    match format {
+        KeyFormat::Hex => Ok(hex::encode(public_key.serialize())),
+        KeyFormat::Sec1Pem | KeyFormat::Pkcs8Pem => {
+            let point = k256::PublicKey::from_sec1_bytes(&public_key.serialize())
+                .map_err(|_| CryptoError::InvalidKey("invalid public key".to_string()))?;
+            let der = point.to_public_key_der().map_err(|e| CryptoError::InvalidKey(e.to_string()))?;
+            Ok(pem_encode(SPKI_LABEL, der.as_bytes()).to_string())
+        }
+        KeyFormat::Jwk => {
+            let serialized = public_key.serialize();
+            let jwk = Jwk {
+                kty: "EC".to_string(),
+                crv: "secp256k1".to_string(),
+                x: URL_SAFE_NO_PAD.encode(&serialized[1..33]),
+                y: URL_SAFE_NO_PAD.encode(&serialized[33..]),
+                d: None,
+            };
+            serde_json::to_string(&jwk).map_err(|e| CryptoError::InvalidKey(e.to_string()))
+        }
+    }
+}
+
+/// Decode a public key. PEM and JWK input may also be a private key, in which
+/// case the public half is returned.
+pub fn decode_public_key(data: &str, format: KeyFormat) -> Result<PublicKey, CryptoError> {
+    match format {
+        KeyFormat::Hex => parse_public_key(data),
+        KeyFormat::Sec1Pem | KeyFormat::Pkcs8Pem => {
+            let (label, der) = pem_decode(data)?;
+            if label == SPKI_LABEL {
+                let point = k256::PublicKey::from_public_key_der(&der)
+                    .map_err(|e| CryptoError::InvalidKey(format!("invalid SPKI public key: {}", e)))?;
+                from_k256_public(&point)
+            } else {
+                Ok(KeyPair::decode(data, format)?.public_key())
+            }
+        }
+        KeyFormat::Jwk => jwk_public_key(&parse_jwk(data)?),
+    }
+}
+
+/// A secp256k1 key pair usable for both ECIES decryption and Ethereum signing.
+#[derive(Clone)]
+pub struct KeyPair {
+    secret_key: SecretKey,
+    public_key: PublicKey,
+}
+
+impl std::fmt::Debug for KeyPair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyPair")
+            .field("address", &self.address())
+            .field("fingerprint", &key_fingerprint(&self.public_key))
+            .finish_non_exhaustive()
+    }
+}
+
+impl KeyPair {
+    pub fn generate() -> Self {
+        let (secret_key, public_key) = ecies::utils::generate_keypair();
+        Self { secret_key, public_key }
+    }
+
+    pub fn from_secret_key(secret_key: SecretKey) -> Self {
+        let public_key = PublicKey::from_secret_key(&secret_key);
+        Self { secret_key, public_key }
+    }
+
+    pub fn secret_key(&self) -> &SecretKey {
+        &self.secret_key
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    /// The Ethereum address this key signs as.
+    pub fn address(&self) -> Address {
+        public_key_to_address(&self.public_key)
+    }
+
+    /// A wallet signing with the same key. Set the chain id with
+    /// `Signer::with_chain_id` before signing transactions.
+    pub fn to_wallet(&self) -> Result<LocalWallet, CryptoError> {
+        LocalWallet::from_bytes(Zeroizing::new(self.secret_key.serialize()).as_ref())
+            .map_err(|e| CryptoError::InvalidKey(e.to_string()))
+    }
+
+    pub fn from_wallet(wallet: &LocalWallet) -> Result<Self, CryptoError> {
+        let bytes = Zeroizing::new(wallet.signer().to_bytes());
+        let secret_key =
+            SecretKey::parse_slice(&bytes).map_err(|_| CryptoError::InvalidKey("invalid wallet key".to_string()))?;
+        Ok(Self::from_secret_key(secret_key))
+    }
+
+    /// Encode the secret key.
+    pub fn encode(&self, format: KeyFormat) -> Result<Zeroizing<String>, CryptoError> {
+        match format {
+            KeyFormat::Hex => Ok(Zeroizing::new(hex::encode(Zeroizing::new(self.secret_key.serialize())))),
+            KeyFormat::Sec1Pem => {
+                let der = to_k256_secret(&self.secret_key)?
+                    .to_sec1_der()
+                    .map_err(|e| CryptoError::InvalidKey(e.to_string()))?;
+                Ok(pem_encode(SEC1_LABEL, &der))
+            }
+            KeyFormat::Pkcs8Pem => {
+                let der = to_k256_secret(&self.secret_key)?
+                    .to_pkcs8_der()
+                    .map_err(|e| CryptoError::InvalidKey(e.to_string()))?;
+                Ok(pem_encode(PKCS8_LABEL, der.as_bytes()))
+            }
+            KeyFormat::Jwk => {
+                let serialized = self.public_key.serialize();
+                let jwk = Jwk {
+                    kty: "EC".to_string(),
+                    crv: "secp256k1".to_string(),
+                    x: URL_SAFE_NO_PAD.encode(&serialized[1..33]),
+                    y: URL_SAFE_NO_PAD.encode(&serialized[33..]),
+                    d: Some(URL_SAFE_NO_PAD.encode(Zeroizing::new(self.secret_key.serialize()))),
+                };
+                let json = serde_json::to_string(&jwk).map_err(|e| CryptoError::InvalidKey(e.to_string()))?;
+                Ok(Zeroizing::new(json))
+            }
+        }
+    }
+
+    /// Decode a secret key. PEM input is accepted as either SEC1 or PKCS#8
+    /// whichever PEM format is requested.
+    pub fn decode(data: &str, format: KeyFormat) -> Result<Self, CryptoError> {
+        let secret_key = match format {
+            KeyFormat::Hex => parse_secret_key(data)?,
+            KeyFormat::Sec1Pem | KeyFormat::Pkcs8Pem => {
+                let (label, der) = pem_decode(data)?;
+                let key = match label.as_str() {
+                    SEC1_LABEL => k256::SecretKey::from_sec1_der(&der)
+                        .map_err(|_| CryptoError::InvalidKey("invalid SEC1 private key".to_string()))?,
+                    PKCS8_LABEL => k256::SecretKey::from_pkcs8_der(&der)
+                        .map_err(|_| CryptoError::InvalidKey("invalid PKCS#8 private key".to_string()))?,
+                    other => return Err(CryptoError::InvalidKey(format!("unexpected PEM label {}", other))),
+                };
+                from_k256_secret(&key)?
+            }
+            KeyFormat::Jwk => {
+                let jwk = parse_jwk(data)?;
+                let d = jwk.d.as_deref().ok_or_else(|| CryptoError::InvalidKey("JWK has no private key".to_string()))?;
+                let secret_key = SecretKey::parse_slice(&jwk_coordinate(d)?)
+                    .map_err(|_| CryptoError::InvalidKey("invalid JWK private key".to_string()))?;
+                if PublicKey::from_secret_key(&secret_key) != jwk_public_key(&jwk)? {
+                    return Err(CryptoError::InvalidKey("JWK public key doesn't match its private key".to_string()));
+                }
+                secret_key
+            }
+        };
+        Ok(Self::from_secret_key(secret_key))
+    }
+
+    /// Write the secret key to `path` with 0600 permissions.
+    pub fn save(&self, path: &Path, format: KeyFormat) -> Result<(), CryptoError> {
+        write_private_file(path, self.encode(format)?.as_bytes())?;
+        log::debug!("saved key {} to {}", key_fingerprint(&self.public_key), path.display());
+        Ok(())
+    }
+
+    pub fn load(path: &Path, format: KeyFormat) -> Result<Self, CryptoError> {
+        let data = Zeroizing::new(std::fs::read_to_string(path)?);
+        Self::decode(&data, format)
+    }
+
+    /// Write the public key to `path`.
+    pub fn save_public(&self, path: &Path, format: KeyFormat) -> Result<(), CryptoError> {
+        std::fs::write(path, encode_public_key(&self.public_key, format)?)?;
+        Ok(())
+    }
+
+    /// Write the secret key to `dir/name` as a password-protected V3 keystore
+    /// with 0600 permissions, returning the file path.
+    ///
+    /// `eth_keystore` creates files with default permissions, so the keystore
+    /// is written into a fresh 0700 staging directory, tightened, and then
+    /// moved into place. It is never readable by other users along the way.
+    pub fn save_encrypted(&self, dir: &Path, name: &str, password: &str) -> Result<PathBuf, CryptoError> {
+        let staging = dir.join(format!(".{}.{:016x}.tmp", name, thread_rng().gen::<u64>()));
+        let mut builder = std::fs::DirBuilder::new();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            builder.mode(0o700);
+        }
+        builder.create(&staging)?;
+
+        let path = dir.join(name);
+        let result = self.write_keystore(&staging, name, password).and_then(|staged| {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+            }
+            std::fs::rename(&staged, &path)?;
+            Ok(())
+        });
+        let _ = std::fs::remove_dir_all(&staging);
+        result?;
+        log::debug!("saved encrypted key {} to {}", key_fingerprint(&self.public_key), path.display());
+        Ok(path)
+    }
+
+    fn write_keystore(&self, dir: &Path, name: &str, password: &str) -> Result<PathBuf, CryptoError> {
+        let secret = Zeroizing::new(self.secret_key.serialize());
+        eth_keystore::encrypt_key(dir, &mut thread_rng(), secret.as_ref(), password, Some(name))
+            .map_err(|e| CryptoError::Encryption(e.to_string()))?;
+        Ok(dir.join(name))
+    }
+
+    /// Open a V3 keystore. A wrong password fails with `AuthenticationFailed`.
+    pub fn load_encrypted(path: &Path, password: &str) -> Result<Self, CryptoError> {
+        let secret = Zeroizing::new(eth_keystore::decrypt_key(path, password).map_err(|e| match e {
+            eth_keystore::KeystoreError::MacMismatch => CryptoError::AuthenticationFailed,
+            eth_keystore::KeystoreError::StdIo(msg) => {
+                CryptoError::Io(std::io::Error::other(msg))
+            }
+            other => CryptoError::InvalidKey(other.to_string()),
+        })?);
+        let secret_key =
+            SecretKey::parse_slice(&secret).map_err(|_| CryptoError::InvalidKey("invalid keystore key".to_string()))?;
+        Ok(Self::from_secret_key(secret_key))
+    }
+}
@@ -1 +1,9 @@
-pub mod ec;
\ No newline at end of file
+pub mod ec;
+pub mod envelope;
+mod error;
+pub mod hd;
+pub mod keys;
+pub mod signing;
+pub mod stream;
+
+pub use error::CryptoError;
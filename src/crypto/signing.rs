@@ -0,0 +1,323 @@
+// src/crypto/signing.rs
+
+//! Detached file signatures tied to DIDs.
+//!
+//! A signature covers the signing DID, the file name, size and keccak256
+//! hash, the signing time and a chain id. It is made either over an EIP-191
+//! personal message, which wallets display as readable text, or over an
+//! EIP-712 typed `FileSignature` struct in the `SWTCH` version `1` domain.
+//! Signatures are stored next to the file as `<file>.sig` JSON.
+//!
+//! Verifying checks the signature was made for the expected chain. A valid
+//! signature only proves which key signed; `verify_for_did` also checks that
+//! the key is the owner or a delegate of the DID it claims.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ecies::{PublicKey, SecretKey};
+use ethers::abi::{encode, Token};
+use ethers::prelude::*;
+use ethers::utils::{hash_message, keccak256};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::identity::IdentityManager;
+use crate::utils::unix_timestamp;
+
+use super::envelope::Envelope;
+use super::error::CryptoError;
+
+pub const SIGNATURE_FORMAT_VERSION: u8 = 1;
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+const EIP712_DOMAIN_TYPE: &str = "EIP712Domain(string name,string version,uint256 chainId)";
+const FILE_SIGNATURE_TYPE: &str =
+    "FileSignature(address did,string fileName,uint256 size,bytes32 contentHash,uint256 timestamp)";
+const EIP712_DOMAIN_NAME: &str = "SWTCH";
+const EIP712_DOMAIN_VERSION: &str = "1";
+
+/// How the payload is turned into the hash that gets signed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureScheme {
+    Eip191,
+    Eip712,
+}
+
+/// The statement a file signature makes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileSignaturePayload {
+    pub did: Address,
+    /// The name the file was signed under. Informational only: renaming a
+    /// file doesn't invalidate its signature.
+    pub file_name: String,
+    pub size: u64,
+    pub content_hash: H256,
+    pub timestamp: u64,
+    /// Chain the signature is bound to: the EIP-712 domain chain id, and a
+    /// line of the EIP-191 message.
+    pub chain_id: u64,
+}
+
+impl FileSignaturePayload {
+    pub fn new(did: Address, file_name: &str, data: &[u8], chain_id: u64) -> Self {
+        Self {
+            did,
+            file_name: file_name.to_string(),
+            size: data.len() as u64,
+            content_hash: H256::from(keccak256(data)),
+            timestamp: unix_timestamp(),
+            chain_id,
+        }
+    }
+
+    /// The EIP-191 personal message. The file name is quoted and escaped so
+    /// it can't inject lines that look like other fields.
+    pub fn message(&self) -> String {
+        format!(
+            "SWTCH file signature\nDID: {:?}\nFile: {:?}\nSize: {}\nHash: {:?}\nTimestamp: {}\nChain ID: {}",
+            self.did, self.file_name, self.size, self.content_hash, self.timestamp, self.chain_id
+        )
+    }
+
+    /// The EIP-712 digest of the typed `FileSignature` struct.
+    pub fn eip712_hash(&self) -> H256 {
+        let domain_separator = keccak256(encode(&[
+            Token::FixedBytes(keccak256(EIP712_DOMAIN_TYPE).to_vec()),
+            Token::FixedBytes(keccak256(EIP712_DOMAIN_NAME).to_vec()),
+            Token::FixedBytes(keccak256(EIP712_DOMAIN_VERSION).to_vec()),
+            Token::Uint(U256::from(self.chain_id)),
+        ]));
+        let struct_hash = keccak256(encode(&[
+            Token::FixedBytes(keccak256(FILE_SIGNATURE_TYPE).to_vec()),
+            Token::Address(self.did),
+            Token::FixedBytes(keccak256(self.file_name.as_bytes()).to_vec()),
+            Token::Uint(U256::from(self.size)),
+            Token::FixedBytes(self.content_hash.as_bytes().to_vec()),
+            Token::Uint(U256::from(self.timestamp)),
+        ]));
+        let mut digest_input = Vec::with_capacity(66);
+        digest_input.extend_from_slice(&[0x19, 0x01]);
+        digest_input.extend_from_slice(&domain_separator);
+        digest_input.extend_from_slice(&struct_hash);
+        H256::from(keccak256(digest_input))
+    }
+
+    /// The hash that is signed under `scheme`.
+    pub fn digest(&self, scheme: SignatureScheme) -> H256 {
+        match scheme {
+            SignatureScheme::Eip191 => hash_message(self.message()),
+            SignatureScheme::Eip712 => self.eip712_hash(),
+        }
+    }
+}
+
+/// A detached signature over a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileSignature {
+    pub version: u8,
+    pub scheme: SignatureScheme,
+    pub payload: FileSignaturePayload,
+    pub signer: Address,
+    /// The 65 byte `r || s || v` signature, hex encoded.
+    pub signature: String,
+}
+
+impl FileSignature {
+    /// Sign `data` as `file_name` on behalf of `did`.
+    pub fn sign(
+        data: &[u8],
+        file_name: &str,
+        did: Address,
+        wallet: &LocalWallet,
+        scheme: SignatureScheme,
+    ) -> Result<Self, CryptoError> {
+        let payload = FileSignaturePayload::new(did, file_name, data, wallet.chain_id());
+        let signature = wallet
+            .sign_hash(payload.digest(scheme))
+            .map_err(|e| CryptoError::InvalidSignature(e.to_string()))?;
+        log::debug!("signed {} for {:?} as {:?}", file_name, did, wallet.address());
+        Ok(Self {
+            version: SIGNATURE_FORMAT_VERSION,
+            scheme,
+            payload,
+            signer: wallet.address(),
+            signature: hex::encode(signature.to_vec()),
+        })
+    }
+
+    pub fn sign_file(path: &Path, did: Address, wallet: &LocalWallet, scheme: SignatureScheme) -> Result<Self, CryptoError> {
+        let data = Zeroizing::new(fs::read(path)?);
+        Self::sign(&data, &file_name(path), did, wallet, scheme)
+    }
+
+    /// Check that the signature covers exactly `data`, was made by `signer`
+    /// and is bound to `expected_chain_id`.
+    pub fn verify(&self, data: &[u8], expected_chain_id: u64) -> Result<(), CryptoError> {
+        self.verify_signature(data)?;
+        self.check_chain(expected_chain_id)
+    }
+
+    fn check_chain(&self, expected_chain_id: u64) -> Result<(), CryptoError> {
+        if self.payload.chain_id != expected_chain_id {
+            return Err(CryptoError::ChainMismatch { expected: expected_chain_id, found: self.payload.chain_id });
+        }
+        Ok(())
+    }
+
+    fn verify_signature(&self, data: &[u8]) -> Result<(), CryptoError> {
+        if self.version != SIGNATURE_FORMAT_VERSION {
+            return Err(CryptoError::UnsupportedVersion(self.version));
+        }
+        if self.payload.size != data.len() as u64 || self.payload.content_hash != H256::from(keccak256(data)) {
+            return Err(CryptoError::InvalidSignature("content doesn't match the signed hash".to_string()));
+        }
+        let bytes = hex::decode(self.signature.trim_start_matches("0x"))
+            .map_err(|_| CryptoError::InvalidSignature("signature is not hex".to_string()))?;
+        let signature =
+            Signature::try_from(bytes.as_slice()).map_err(|e| CryptoError::InvalidSignature(e.to_string()))?;
+        let recovered = signature
+            .recover(self.payload.digest(self.scheme))
+            .map_err(|e| CryptoError::InvalidSignature(e.to_string()))?;
+        if recovered != self.signer {
+            return Err(CryptoError::InvalidSignature(format!(
+                "signed by {:?}, not {:?}",
+                recovered, self.signer
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn verify_file(&self, path: &Path, expected_chain_id: u64) -> Result<(), CryptoError> {
+        self.verify(&fs::read(path)?, expected_chain_id)
+    }
+
+    /// Verify the signature against the chain `identity` is connected to, and
+    /// that the signer is the owner or a delegate of the DID named in the
+    /// payload.
+    pub async fn verify_for_did<M: Middleware + 'static>(
+        &self,
+        data: &[u8],
+        identity: &IdentityManager<M>,
+    ) -> Result<(), CryptoError> {
+        self.verify_signature(data)?;
+        let chain_id = identity
+            .contract
+            .client()
+            .get_chainid()
+            .await
+            .map_err(|e| CryptoError::Identity(e.to_string()))?;
+        self.check_chain(chain_id.as_u64())?;
+        let did = self.payload.did;
+        let authorized = identity
+            .is_owner_or_delegate(did, self.signer)
+            .await
+            .map_err(|e| CryptoError::Identity(e.to_string()))?;
+        if !authorized {
+            return Err(CryptoError::UnauthorizedSigner { did, signer: self.signer });
+        }
+        Ok(())
+    }
+
+    /// Where the detached signature of `path` is stored: `<path>.sig`.
+    pub fn signature_path(path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(".");
+        name.push(SIGNATURE_EXTENSION);
+        PathBuf::from(name)
+    }
+
+    pub fn to_json(&self) -> Result<String, CryptoError> {
+        serde_json::to_string_pretty(self).map_err(|e| CryptoError::InvalidSignature(e.to_string()))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, CryptoError> {
+        serde_json::from_str(json).map_err(|e| CryptoError::InvalidSignature(format!("malformed signature file: {}", e)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), CryptoError> {
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, CryptoError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+/// Sign `data` and encrypt it, together with its signature, to `recipients`.
+/// The sealed plaintext is `signature_len u32 | signature JSON | data`.
+pub fn sign_and_encrypt(
+    data: &[u8],
+    file_name: &str,
+    did: Address,
+    wallet: &LocalWallet,
+    scheme: SignatureScheme,
+    recipients: &[PublicKey],
+) -> Result<Envelope, CryptoError> {
+    let signature = FileSignature::sign(data, file_name, did, wallet, scheme)?.to_json()?;
+    let mut plaintext = Zeroizing::new(Vec::with_capacity(4 + signature.len() + data.len()));
+    plaintext.extend_from_slice(&(signature.len() as u32).to_be_bytes());
+    plaintext.extend_from_slice(signature.as_bytes());
+    plaintext.extend_from_slice(data);
+    Envelope::seal(&plaintext, recipients)
+}
+
+/// Open an envelope made by `sign_and_encrypt` and verify the signature over
+/// its contents for `expected_chain_id`. Check the signer against the DID
+/// with `verify_for_did`.
+pub fn decrypt_and_verify(
+    envelope: &Envelope,
+    secret_key: &SecretKey,
+    expected_chain_id: u64,
+) -> Result<(Zeroizing<Vec<u8>>, FileSignature), CryptoError> {
+    let plaintext = envelope.open(secret_key)?;
+    if plaintext.len() < 4 {
+        return Err(CryptoError::Corrupted("signed payload is too short".to_string()));
+    }
+    let signature_len = u32::from_be_bytes([plaintext[0], plaintext[1], plaintext[2], plaintext[3]]) as usize;
+    let signature_end = 4usize
+        .checked_add(signature_len)
+        .filter(|end| *end <= plaintext.len())
+        .ok_or_else(|| CryptoError::Corrupted("signature length exceeds payload".to_string()))?;
+    let signature_json = std::str::from_utf8(&plaintext[4..signature_end])
+        .map_err(|_| CryptoError::Corrupted("signature is not UTF-8".to_string()))?;
+    let signature = FileSignature::from_json(signature_json)?;
+    let data = Zeroizing::new(plaintext[signature_end..].to_vec());
+    signature.verify(&data, expected_chain_id)?;
+    Ok((data, signature))
+}
+
+/// Sign a file and encrypt it with its signature to `recipients`.
+pub fn sign_and_encrypt_file(
+    path: &Path,
+    did: Address,
+    wallet: &LocalWallet,
+    scheme: SignatureScheme,
+    recipients: &[PublicKey],
+    output_path: &Path,
+) -> Result<(), CryptoError> {
+    let data = Zeroizing::new(fs::read(path)?);
+    let envelope = sign_and_encrypt(&data, &file_name(path), did, wallet, scheme, recipients)?;
+    fs::write(output_path, envelope.to_bytes())?;
+    Ok(())
+}
+
+/// Decrypt a file made by `sign_and_encrypt_file`, verify it and write the
+/// contents to `output_path`. Nothing is written if verification fails.
+pub fn decrypt_and_verify_file(
+    path: &Path,
+    secret_key: &SecretKey,
+    expected_chain_id: u64,
+    output_path: &Path,
+) -> Result<FileSignature, CryptoError> {
+    let envelope = Envelope::from_bytes(&fs::read(path)?)?;
+    let (data, signature) = decrypt_and_verify(&envelope, secret_key, expected_chain_id)?;
+    fs::write(output_path, data.as_slice())?;
+    Ok(signature)
+}
@@ -0,0 +1,229 @@
+// src/crypto/stream.rs
+
+//! Streaming, chunked encryption for payloads too large to hold in memory.
+//!
+//! A random 256-bit data key is wrapped to the recipient with ECIES and the
+//! payload is sealed in AES-256-GCM chunks. Layout:
+//!
+//! ```text
+//! header: "SWTCHENC" | version u8 | chunk_size u32 | key_id [8] | wrapped_len u16 | wrapped_key | nonce_prefix [7]
+//! frame:  final u8 | len u32 | ciphertext (len bytes, including the 16 byte tag)
+//! ```
+//!
+//! Each chunk's nonce is `nonce_prefix | index u32 | final u8` and the whole
+//! header is bound in as associated data, so reordered, duplicated or
+//! truncated chunks fail to authenticate. All integers are big endian.
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use ecies::{PublicKey, SecretKey};
+use ethers::utils::keccak256;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use zeroize::Zeroizing;
+
+use super::error::CryptoError;
+
+pub const STREAM_MAGIC: &[u8; 8] = b"SWTCHENC";
+pub const STREAM_VERSION: u8 = 1;
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+/// Largest chunk size accepted. The header is read before anything is
+/// authenticated, so this bounds what a forged header can make us allocate.
+pub const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+const TAG_SIZE: usize = 16;
+const NONCE_PREFIX_SIZE: usize = 7;
+const FRAME_MORE: u8 = 0;
+const FRAME_FINAL: u8 = 1;
+
+/// A short identifier of a public key, stored in headers so that decryption
+/// with the wrong key fails fast.
+pub fn key_id(public_key: &PublicKey) -> [u8; 8] {
+    let mut id = [0u8; 8];
+    id.copy_from_slice(&keccak256(public_key.serialize())[..8]);
+    id
+}
+
+fn chunk_nonce(prefix: &[u8; NONCE_PREFIX_SIZE], index: u32, last: bool) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..NONCE_PREFIX_SIZE].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_SIZE..11].copy_from_slice(&index.to_be_bytes());
+    nonce[11] = if last { FRAME_FINAL } else { FRAME_MORE };
+    nonce
+}
+
+/// Fill `buf` from `reader`, stopping early only at end of input.
+async fn read_chunk<R: AsyncRead + Unpin>(reader: &mut R, buf: &mut [u8]) -> Result<usize, CryptoError> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = reader.read(&mut buf[filled..]).await?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    Ok(filled)
+}
+
+async fn read_exact_or_truncated<R: AsyncRead + Unpin>(reader: &mut R, buf: &mut [u8]) -> Result<(), CryptoError> {
+    match reader.read_exact(buf).await {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Err(CryptoError::Truncated),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Encrypt everything read from `reader` to `public_key`, writing the stream
+/// format to `writer`. Returns the number of plaintext bytes encrypted.
+pub async fn encrypt_stream<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    public_key: &PublicKey,
+    chunk_size: usize,
+) -> Result<u64, CryptoError>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+        return Err(CryptoError::Encryption(format!("invalid chunk size {}", chunk_size)));
+    }
+
+    let mut data_key = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(data_key.as_mut());
+    let wrapped_key = ecies::encrypt(&public_key.serialize(), data_key.as_ref())
+        .map_err(|e| CryptoError::Encryption(e.to_string()))?;
+    let nonce = Aes256Gcm::generate_nonce(OsRng);
+    let mut nonce_prefix = [0u8; NONCE_PREFIX_SIZE];
+    nonce_prefix.copy_from_slice(&nonce[..NONCE_PREFIX_SIZE]);
+
+    let mut header = Vec::with_capacity(32 + wrapped_key.len());
+    header.extend_from_slice(STREAM_MAGIC);
+    header.push(STREAM_VERSION);
+    header.extend_from_slice(&(chunk_size as u32).to_be_bytes());
+    header.extend_from_slice(&key_id(public_key));
+    header.extend_from_slice(&(wrapped_key.len() as u16).to_be_bytes());
+    header.extend_from_slice(&wrapped_key);
+    header.extend_from_slice(&nonce_prefix);
+    writer.write_all(&header).await?;
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(data_key.as_ref()));
+    let mut current = Zeroizing::new(vec![0u8; chunk_size]);
+    let mut next = Zeroizing::new(vec![0u8; chunk_size]);
+    let mut current_len = read_chunk(reader, &mut current).await?;
+    let mut index: u32 = 0;
+    let mut total: u64 = 0;
+
+    loop {
+        let next_len = if current_len == chunk_size { read_chunk(reader, &mut next).await? } else { 0 };
+        let last = next_len == 0;
+
+        let nonce = chunk_nonce(&nonce_prefix, index, last);
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: &current[..current_len], aad: &header })
+            .map_err(|e| CryptoError::Encryption(e.to_string()))?;
+        writer.write_all(&[if last { FRAME_FINAL } else { FRAME_MORE }]).await?;
+        writer.write_all(&(ciphertext.len() as u32).to_be_bytes()).await?;
+        writer.write_all(&ciphertext).await?;
+        total += current_len as u64;
+
+        if last {
+            break;
+        }
+        std::mem::swap(&mut current, &mut next);
+        current_len = next_len;
+        index = index
+            .checked_add(1)
+            .ok_or_else(|| CryptoError::Encryption("too many chunks".to_string()))?;
+    }
+
+    writer.flush().await?;
+    Ok(total)
+}
+
+/// Decrypt a stream produced by `encrypt_stream`. Plaintext is written to
+/// `writer` chunk by chunk as each chunk authenticates; on error the output
+/// holds only authenticated chunks but must still be discarded. Returns the
+/// number of plaintext bytes written.
+pub async fn decrypt_stream<R, W>(reader: &mut R, writer: &mut W, secret_key: &SecretKey) -> Result<u64, CryptoError>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut fixed = [0u8; 8 + 1 + 4 + 8 + 2];
+    read_exact_or_truncated(reader, &mut fixed).await?;
+    if &fixed[..8] != STREAM_MAGIC {
+        return Err(CryptoError::InvalidHeader("missing stream magic".to_string()));
+    }
+    if fixed[8] != STREAM_VERSION {
+        return Err(CryptoError::UnsupportedVersion(fixed[8]));
+    }
+    let chunk_size = u32::from_be_bytes(fixed[9..13].try_into().unwrap()) as usize;
+    if chunk_size == 0 {
+        return Err(CryptoError::InvalidHeader("zero chunk size".to_string()));
+    }
+    if chunk_size > MAX_CHUNK_SIZE {
+        return Err(CryptoError::InvalidHeader(format!("chunk size {} exceeds the maximum of {}", chunk_size, MAX_CHUNK_SIZE)));
+    }
+    if fixed[13..21] != key_id(&PublicKey::from_secret_key(secret_key)) {
+        return Err(CryptoError::WrongKey);
+    }
+    let wrapped_len = u16::from_be_bytes(fixed[21..23].try_into().unwrap()) as usize;
+
+    let mut rest = vec![0u8; wrapped_len + NONCE_PREFIX_SIZE];
+    read_exact_or_truncated(reader, &mut rest).await?;
+    let mut header = fixed.to_vec();
+    header.extend_from_slice(&rest);
+
+    let secret_key_bytes = Zeroizing::new(secret_key.serialize());
+    let data_key = Zeroizing::new(
+        ecies::decrypt(secret_key_bytes.as_ref(), &rest[..wrapped_len])
+            .map_err(|_| CryptoError::InvalidHeader("data key failed to unwrap".to_string()))?,
+    );
+    if data_key.len() != 32 {
+        return Err(CryptoError::InvalidHeader("data key has the wrong length".to_string()));
+    }
+    let mut nonce_prefix = [0u8; NONCE_PREFIX_SIZE];
+    nonce_prefix.copy_from_slice(&rest[wrapped_len..]);
+
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&data_key));
+    let mut frame = vec![0u8; chunk_size + TAG_SIZE];
+    let mut index: u32 = 0;
+    let mut total: u64 = 0;
+
+    loop {
+        let mut frame_header = [0u8; 5];
+        read_exact_or_truncated(reader, &mut frame_header).await?;
+        let last = match frame_header[0] {
+            FRAME_MORE => false,
+            FRAME_FINAL => true,
+            _ => return Err(CryptoError::ChunkAuthentication { index }),
+        };
+        let len = u32::from_be_bytes(frame_header[1..5].try_into().unwrap()) as usize;
+        if !(TAG_SIZE..=chunk_size + TAG_SIZE).contains(&len) {
+            return Err(CryptoError::ChunkAuthentication { index });
+        }
+        read_exact_or_truncated(reader, &mut frame[..len]).await?;
+
+        let nonce = chunk_nonce(&nonce_prefix, index, last);
+        let plaintext = Zeroizing::new(
+            cipher
+                .decrypt(Nonce::from_slice(&nonce), Payload { msg: &frame[..len], aad: &header })
+                .map_err(|_| CryptoError::ChunkAuthentication { index })?,
+        );
+        writer.write_all(&plaintext).await?;
+        total += plaintext.len() as u64;
+
+        if last {
+            break;
+        }
+        index = index.checked_add(1).ok_or(CryptoError::ChunkAuthentication { index })?;
+    }
+
+    let mut trailing = [0u8; 1];
+    if reader.read(&mut trailing).await? != 0 {
+        return Err(CryptoError::TrailingData);
+    }
+    writer.flush().await?;
+    Ok(total)
+}
@@ -1,8 +1,6 @@
 // src/identity/manager.rs
 use super::models::Identity;
 use ethers::prelude::*;
-use ethers::providers::{Provider, Http};
-use ethers::core::k256::ecdsa::SigningKey;
 use ethers::signers::{LocalWallet, Signer};
 use std::sync::Arc;
 
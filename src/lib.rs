@@ -10,6 +10,7 @@ pub mod utils;
 
 pub use crate::context::{ContextManager, Config, BlockchainConfig, WalletConfig, ChainType, NetworkType, TestnetType};
 pub use crate::identity::{IdentityManager, Identity};
+pub use crate::net::{NetworkError, NetworkManager, NetworkService, ProviderPage};
 pub use crate::transactions::TransactionReceipt as SWTCHTransaction;
 
 use ethers::prelude::*;
@@ -20,6 +21,13 @@ use std::sync::Arc;
 pub struct SwtchSDK {
     context_manager: ContextManager,
     identity_manager: Option<IdentityManager<Provider<Http>>>,
+    network_manager: Option<Arc<NetworkManager<Provider<Http>>>>,
+}
+
+impl Default for SwtchSDK {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SwtchSDK {
@@ -27,6 +35,7 @@ impl SwtchSDK {
         Self {
             context_manager: ContextManager::new(),
             identity_manager: None,
+            network_manager: None,
         }
     }
 
@@ -110,5 +119,39 @@ impl SwtchSDK {
             .ok_or("IdentityManager not initialized")?
             .verify_signature(message, signature, signer))
     }
-}
 
+    pub async fn initialize_network_manager(&mut self, contract_addr: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let contract_address: Address = contract_addr.parse()?;
+        let config = self.context_manager.get_active_config().ok_or("No active configuration")?;
+        let provider = Provider::<Http>::try_from(config.blockchain.provider_url.clone())?;
+
+        self.network_manager = Some(Arc::new(NetworkManager::new(contract_address, Arc::new(provider))));
+        Ok(())
+    }
+
+    /// The shared manager, for building a `DiscoveryClient` or `ProviderSelector`.
+    pub fn network_manager(&self) -> Option<Arc<NetworkManager<Provider<Http>>>> {
+        self.network_manager.clone()
+    }
+
+    fn require_network_manager(&self) -> Result<&NetworkManager<Provider<Http>>, Box<dyn std::error::Error>> {
+        Ok(self.network_manager.as_deref().ok_or("NetworkManager not initialized")?)
+    }
+
+    pub async fn get_network_service(&self, provider: &str) -> Result<NetworkService, Box<dyn std::error::Error>> {
+        let provider: Address = provider.parse()?;
+        Ok(self.require_network_manager()?.get_network_service(provider).await?)
+    }
+
+    /// Fetch several providers' services in batched multicalls.
+    pub async fn get_network_services(
+        &self,
+        providers: &[Address],
+    ) -> Result<Vec<(Address, Result<NetworkService, NetworkError<Provider<Http>>>)>, Box<dyn std::error::Error>> {
+        Ok(self.require_network_manager()?.get_network_services(providers).await?)
+    }
+
+    pub async fn get_service_providers_page(&self, offset: u64, limit: u64) -> Result<ProviderPage, Box<dyn std::error::Error>> {
+        Ok(self.require_network_manager()?.get_service_providers_page(offset, limit).await?)
+    }
+}
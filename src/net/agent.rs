@@ -0,0 +1,269 @@
+// src/net/agent.rs
+
+use async_trait::async_trait;
+use ethers::prelude::*;
+use ethers::utils::{hash_message, keccak256};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+use crate::utils::unix_timestamp;
+
+use super::descriptor::{canonical_json, DescriptorError, ServiceDescriptor, SignedServiceDescriptor};
+use super::error::NetworkError;
+use super::manager::NetworkManager;
+
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A provider's off-chain liveness signal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Heartbeat {
+    pub provider: Address,
+    /// Increases by one with every heartbeat the agent sends.
+    pub sequence: u64,
+    pub timestamp: u64,
+    /// `descriptor_hash` of the agent's descriptor. After registration this
+    /// is the registered copy, so listeners can hash the registry entry and
+    /// tell when it is stale.
+    pub descriptor_hash: H256,
+    /// Current number of connected clients, if the operator reports it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load: Option<u32>,
+}
+
+/// keccak256 of a descriptor's canonical JSON, as carried by heartbeats.
+pub fn descriptor_hash(descriptor: &ServiceDescriptor) -> Result<H256, DescriptorError> {
+    Ok(H256::from(keccak256(canonical_json(descriptor)?)))
+}
+
+/// A heartbeat with the provider's EIP-191 signature over its canonical JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedHeartbeat {
+    pub heartbeat: Heartbeat,
+    pub signature: String,
+}
+
+impl SignedHeartbeat {
+    pub fn sign(heartbeat: Heartbeat, wallet: &LocalWallet) -> Result<Self, DescriptorError> {
+        let signature = wallet
+            .sign_hash(hash_message(canonical_json(&heartbeat)?))
+            .map_err(|e| DescriptorError::Signature(e.to_string()))?;
+        Ok(Self { heartbeat, signature: hex::encode(signature.to_vec()) })
+    }
+
+    /// Check the heartbeat was signed by the provider it names.
+    pub fn verify(&self) -> Result<(), DescriptorError> {
+        let bytes = hex::decode(self.signature.trim_start_matches("0x"))
+            .map_err(|_| DescriptorError::Signature("signature is not hex".to_string()))?;
+        let signature = Signature::try_from(bytes.as_slice()).map_err(|e| DescriptorError::Signature(e.to_string()))?;
+        let signer = signature
+            .recover(hash_message(canonical_json(&self.heartbeat)?))
+            .map_err(|e| DescriptorError::Signature(e.to_string()))?;
+        if signer != self.heartbeat.provider {
+            return Err(DescriptorError::ProviderMismatch { expected: self.heartbeat.provider, found: signer });
+        }
+        Ok(())
+    }
+}
+
+/// Where heartbeats are published, e.g. a gossip topic or an HTTP collector.
+#[async_trait]
+pub trait HeartbeatSink: Send + Sync {
+    async fn publish(&self, heartbeat: &SignedHeartbeat) -> Result<(), String>;
+}
+
+/// A sink that forwards heartbeats to an in-process channel.
+pub struct ChannelSink {
+    sender: mpsc::Sender<SignedHeartbeat>,
+}
+
+impl ChannelSink {
+    pub fn new(sender: mpsc::Sender<SignedHeartbeat>) -> Self {
+        Self { sender }
+    }
+}
+
+#[async_trait]
+impl HeartbeatSink for ChannelSink {
+    async fn publish(&self, heartbeat: &SignedHeartbeat) -> Result<(), String> {
+        self.sender.send(heartbeat.clone()).await.map_err(|_| "heartbeat channel closed".to_string())
+    }
+}
+
+/// What `ProviderAgent::ensure_registered` had to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Registration {
+    Registered(TransactionReceipt),
+    Updated(TransactionReceipt),
+    /// The registry already holds an equivalent descriptor.
+    Unchanged,
+}
+
+/// Keeps a provider's registry entry in sync with its descriptor and
+/// publishes signed heartbeats while the service runs.
+pub struct ProviderAgent<M: Middleware> {
+    manager: Arc<NetworkManager<M>>,
+    wallet: LocalWallet,
+    descriptor: ServiceDescriptor,
+    sink: Arc<dyn HeartbeatSink>,
+    heartbeat_interval: Duration,
+    updates: Option<watch::Receiver<ServiceDescriptor>>,
+    publish_updates: bool,
+    deregister_on_shutdown: bool,
+    sequence: u64,
+}
+
+impl<M: Middleware + 'static> ProviderAgent<M> {
+    /// The descriptor's provider must be the wallet's address.
+    pub fn new(
+        manager: Arc<NetworkManager<M>>,
+        wallet: LocalWallet,
+        descriptor: ServiceDescriptor,
+        sink: Arc<dyn HeartbeatSink>,
+    ) -> Self {
+        Self {
+            manager,
+            wallet,
+            descriptor,
+            sink,
+            heartbeat_interval: DEFAULT_HEARTBEAT_INTERVAL,
+            updates: None,
+            publish_updates: true,
+            deregister_on_shutdown: true,
+            sequence: 0,
+        }
+    }
+
+    pub fn with_heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.heartbeat_interval = interval;
+        self
+    }
+
+    /// Pick up descriptor changes while running. When `publish_on_chain` is
+    /// set, each change is also written to the registry; otherwise it only
+    /// changes the descriptor hash carried by heartbeats.
+    pub fn with_descriptor_updates(mut self, updates: watch::Receiver<ServiceDescriptor>, publish_on_chain: bool) -> Self {
+        self.updates = Some(updates);
+        self.publish_updates = publish_on_chain;
+        self
+    }
+
+    /// Leave the registry entry in place when `run` shuts down.
+    pub fn keep_registration(mut self) -> Self {
+        self.deregister_on_shutdown = false;
+        self
+    }
+
+    pub fn provider(&self) -> Address {
+        self.wallet.address()
+    }
+
+    pub fn descriptor(&self) -> &ServiceDescriptor {
+        &self.descriptor
+    }
+
+    fn signed_descriptor(&self) -> Result<SignedServiceDescriptor, DescriptorError> {
+        let mut descriptor = self.descriptor.clone();
+        descriptor.updated_at = unix_timestamp();
+        descriptor.sign(&self.wallet)
+    }
+
+    /// Register the descriptor, or update it if the registry holds different
+    /// details. Safe to call repeatedly. Afterwards the agent's descriptor is
+    /// the registered copy, including its `updated_at`.
+    pub async fn ensure_registered(&mut self) -> Result<Registration, NetworkError<M>> {
+        let provider = self.provider();
+        if !self.manager.is_service_provider(provider).await? {
+            let signed = self.signed_descriptor()?;
+            let receipt = self.manager.add_network_service(provider, &signed).await?;
+            self.descriptor = signed.descriptor;
+            return Ok(Registration::Registered(receipt));
+        }
+
+        let details = self.manager.get_service_details(provider).await?;
+        let stored = SignedServiceDescriptor::from_details(&details)
+            .ok()
+            .filter(|stored| stored.verify_for(provider).is_ok())
+            .filter(|stored| ServiceDescriptor { updated_at: self.descriptor.updated_at, ..stored.descriptor.clone() } == self.descriptor);
+        if let Some(stored) = stored {
+            self.descriptor = stored.descriptor;
+            return Ok(Registration::Unchanged);
+        }
+        let signed = self.signed_descriptor()?;
+        let receipt = self.manager.update_network_service(provider, &signed).await?;
+        self.descriptor = signed.descriptor;
+        Ok(Registration::Updated(receipt))
+    }
+
+    /// Sign and publish the next heartbeat.
+    pub async fn heartbeat(&mut self, load: Option<u32>) -> Result<SignedHeartbeat, String> {
+        self.sequence += 1;
+        let heartbeat = Heartbeat {
+            provider: self.provider(),
+            sequence: self.sequence,
+            timestamp: unix_timestamp(),
+            descriptor_hash: descriptor_hash(&self.descriptor).map_err(|e| e.to_string())?,
+            load,
+        };
+        let signed = SignedHeartbeat::sign(heartbeat, &self.wallet).map_err(|e| e.to_string())?;
+        self.sink.publish(&signed).await?;
+        Ok(signed)
+    }
+
+    /// Remove the provider from the registry.
+    pub async fn deregister(&self) -> Result<TransactionReceipt, NetworkError<M>> {
+        self.manager.remove_network_service(self.provider()).await
+    }
+
+    /// Register, then heartbeat every interval until `shutdown` resolves, then
+    /// deregister. Failed heartbeats and descriptor updates are logged and
+    /// retried on the next tick; only registration and deregistration errors
+    /// end the run.
+    pub async fn run<F: Future<Output = ()>>(mut self, shutdown: F) -> Result<Option<TransactionReceipt>, NetworkError<M>> {
+        self.ensure_registered().await?;
+        tokio::pin!(shutdown);
+        let mut ticker = tokio::time::interval(self.heartbeat_interval);
+        let mut updates = self.updates.take();
+
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                _ = ticker.tick() => {
+                    if let Err(e) = self.heartbeat(None).await {
+                        log::warn!("heartbeat for {:?} failed: {}", self.provider(), e);
+                    }
+                }
+                update = next_update(&mut updates) => match update {
+                    Some(descriptor) => {
+                        self.descriptor = descriptor;
+                        if self.publish_updates {
+                            if let Err(e) = self.ensure_registered().await {
+                                log::warn!("updating descriptor for {:?} failed: {}", self.provider(), e);
+                            }
+                        }
+                    }
+                    None => updates = None,
+                },
+            }
+        }
+
+        if self.deregister_on_shutdown {
+            return self.deregister().await.map(Some);
+        }
+        Ok(None)
+    }
+}
+
+/// The next descriptor from `updates`, `None` once the sender is dropped, or
+/// never if there is no update channel.
+async fn next_update(updates: &mut Option<watch::Receiver<ServiceDescriptor>>) -> Option<ServiceDescriptor> {
+    match updates {
+        Some(receiver) => match receiver.changed().await {
+            Ok(()) => Some(receiver.borrow_and_update().clone()),
+            Err(_) => None,
+        },
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,303 @@
+// src/net/descriptor.rs
+
+use ethers::prelude::*;
+use ethers::utils::hash_message;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::utils::unix_timestamp;
+
+/// Schema version written into every descriptor.
+pub const DESCRIPTOR_SCHEMA_VERSION: u32 = 1;
+/// Upper bound on the stored JSON, which lives in contract storage.
+pub const MAX_DESCRIPTOR_LEN: usize = 4096;
+
+/// Errors raised while building, signing or verifying a service descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DescriptorError {
+    /// A field is missing or out of range.
+    Invalid(String),
+    /// The stored details are not a signed descriptor.
+    Format(String),
+    UnsupportedVersion(u32),
+    /// The signature is malformed or doesn't match the descriptor.
+    Signature(String),
+    /// The descriptor names a different provider than the one it's stored for,
+    /// or was signed by someone else.
+    ProviderMismatch { expected: Address, found: Address },
+}
+
+impl fmt::Display for DescriptorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DescriptorError::Invalid(msg) => write!(f, "invalid service descriptor: {}", msg),
+            DescriptorError::Format(msg) => write!(f, "malformed service descriptor: {}", msg),
+            DescriptorError::UnsupportedVersion(v) => write!(f, "unsupported descriptor schema version {}", v),
+            DescriptorError::Signature(msg) => write!(f, "invalid descriptor signature: {}", msg),
+            DescriptorError::ProviderMismatch { expected, found } => {
+                write!(f, "descriptor is for {:?}, expected {:?}", found, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DescriptorError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceType {
+    Vpn,
+    Proxy,
+    Relay,
+    Rpc,
+    Storage,
+    Compute,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Tcp,
+    Udp,
+    Http,
+    Https,
+    Ws,
+    Wss,
+    Quic,
+    Wireguard,
+}
+
+/// One address a service can be reached at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Endpoint {
+    pub protocol: Protocol,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Endpoint {
+    pub fn new(protocol: Protocol, host: impl Into<String>, port: u16) -> Self {
+        Self { protocol, host: host.into(), port }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PricingUnit {
+    PerHour,
+    PerGigabyte,
+    PerRequest,
+    PerMonth,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pricing {
+    /// Price per unit in the token's smallest denomination.
+    pub amount: U256,
+    pub unit: PricingUnit,
+    /// ERC-20 token the price is quoted in; `None` for the native currency.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<Address>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capacity {
+    /// Maximum concurrent clients.
+    pub max_clients: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bandwidth_mbps: Option<u32>,
+}
+
+/// What a provider offers and how to reach it. Stored on-chain as the
+/// `serviceDetails` string, wrapped in a `SignedServiceDescriptor`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceDescriptor {
+    pub schema_version: u32,
+    pub provider: Address,
+    pub service_type: ServiceType,
+    pub endpoints: Vec<Endpoint>,
+    pub pricing: Pricing,
+    /// Free-form region code, e.g. `eu-west`.
+    pub region: String,
+    pub capacity: Capacity,
+    /// The provider's software version.
+    pub version: String,
+    pub updated_at: u64,
+}
+
+impl ServiceDescriptor {
+    pub fn new(provider: Address, service_type: ServiceType, pricing: Pricing, region: impl Into<String>) -> Self {
+        Self {
+            schema_version: DESCRIPTOR_SCHEMA_VERSION,
+            provider,
+            service_type,
+            endpoints: Vec::new(),
+            pricing,
+            region: region.into(),
+            capacity: Capacity::default(),
+            version: String::new(),
+            updated_at: unix_timestamp(),
+        }
+    }
+
+    pub fn with_endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.endpoints.push(endpoint);
+        self
+    }
+
+    pub fn with_capacity(mut self, capacity: Capacity) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// The distinct protocols across all endpoints.
+    pub fn protocols(&self) -> Vec<Protocol> {
+        let mut protocols = Vec::new();
+        for endpoint in &self.endpoints {
+            if !protocols.contains(&endpoint.protocol) {
+                protocols.push(endpoint.protocol);
+            }
+        }
+        protocols
+    }
+
+    pub fn validate(&self) -> Result<(), DescriptorError> {
+        if self.schema_version != DESCRIPTOR_SCHEMA_VERSION {
+            return Err(DescriptorError::UnsupportedVersion(self.schema_version));
+        }
+        if self.provider.is_zero() {
+            return Err(DescriptorError::Invalid("provider is the zero address".to_string()));
+        }
+        if self.endpoints.is_empty() {
+            return Err(DescriptorError::Invalid("at least one endpoint is required".to_string()));
+        }
+        for endpoint in &self.endpoints {
+            if endpoint.host.trim().is_empty() || endpoint.host.chars().any(char::is_whitespace) {
+                return Err(DescriptorError::Invalid(format!("invalid endpoint host '{}'", endpoint.host)));
+            }
+            if endpoint.port == 0 {
+                return Err(DescriptorError::Invalid(format!("endpoint {} has port 0", endpoint.host)));
+            }
+        }
+        if self.region.trim().is_empty() {
+            return Err(DescriptorError::Invalid("region is required".to_string()));
+        }
+        if self.version.trim().is_empty() {
+            return Err(DescriptorError::Invalid("version is required".to_string()));
+        }
+        if self.capacity.max_clients == 0 {
+            return Err(DescriptorError::Invalid("max_clients must be positive".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Canonical JSON: object keys sorted, no insignificant whitespace. This
+    /// is the exact byte string that gets signed.
+    pub fn canonical_json(&self) -> Result<String, DescriptorError> {
+        canonical_json(self)
+    }
+
+    /// Validate and sign with the provider's key.
+    pub fn sign(self, wallet: &LocalWallet) -> Result<SignedServiceDescriptor, DescriptorError> {
+        self.validate()?;
+        if wallet.address() != self.provider {
+            return Err(DescriptorError::ProviderMismatch { expected: self.provider, found: wallet.address() });
+        }
+        let signature = wallet
+            .sign_hash(hash_message(self.canonical_json()?))
+            .map_err(|e| DescriptorError::Signature(e.to_string()))?;
+        Ok(SignedServiceDescriptor { descriptor: self, signature: hex::encode(signature.to_vec()) })
+    }
+}
+
+/// A descriptor with the provider's EIP-191 signature over its canonical JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedServiceDescriptor {
+    pub descriptor: ServiceDescriptor,
+    pub signature: String,
+}
+
+impl SignedServiceDescriptor {
+    /// Validate the descriptor and check it was signed by its provider.
+    pub fn verify(&self) -> Result<(), DescriptorError> {
+        self.descriptor.validate()?;
+        let bytes = hex::decode(self.signature.trim_start_matches("0x"))
+            .map_err(|_| DescriptorError::Signature("signature is not hex".to_string()))?;
+        let signature = Signature::try_from(bytes.as_slice()).map_err(|e| DescriptorError::Signature(e.to_string()))?;
+        let signer = signature
+            .recover(hash_message(self.descriptor.canonical_json()?))
+            .map_err(|e| DescriptorError::Signature(e.to_string()))?;
+        if signer != self.descriptor.provider {
+            return Err(DescriptorError::ProviderMismatch { expected: self.descriptor.provider, found: signer });
+        }
+        Ok(())
+    }
+
+    /// Verify, and check the descriptor belongs to `provider`.
+    pub fn verify_for(&self, provider: Address) -> Result<(), DescriptorError> {
+        if self.descriptor.provider != provider {
+            return Err(DescriptorError::ProviderMismatch { expected: provider, found: self.descriptor.provider });
+        }
+        self.verify()
+    }
+
+    /// The `serviceDetails` string stored on-chain.
+    pub fn to_details(&self) -> Result<String, DescriptorError> {
+        let details = canonical_json(self)?;
+        if details.len() > MAX_DESCRIPTOR_LEN {
+            return Err(DescriptorError::Invalid(format!(
+                "descriptor is {} bytes, limit is {}",
+                details.len(),
+                MAX_DESCRIPTOR_LEN
+            )));
+        }
+        Ok(details)
+    }
+
+    /// Parse a `serviceDetails` string. Call `verify` before trusting it.
+    pub fn from_details(details: &str) -> Result<Self, DescriptorError> {
+        serde_json::from_str(details).map_err(|e| DescriptorError::Format(e.to_string()))
+    }
+}
+
+pub(super) fn canonical_json<T: Serialize>(value: &T) -> Result<String, DescriptorError> {
+    let value = serde_json::to_value(value).map_err(|e| DescriptorError::Format(e.to_string()))?;
+    let mut out = String::new();
+    write_canonical(&value, &mut out);
+    Ok(out)
+}
+
+fn write_canonical(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
@@ -0,0 +1,370 @@
+// src/net/discovery.rs
+
+use ethers::prelude::*;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task::JoinSet;
+
+use crate::utils::unix_timestamp;
+
+use super::descriptor::{Endpoint, Protocol, ServiceType};
+use super::error::NetworkError;
+use super::manager::{NetworkManager, NetworkService};
+
+pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+pub const DEFAULT_MAX_CONCURRENT_PROBES: usize = 32;
+
+/// Which services `DiscoveryClient::discover` returns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceFilter {
+    pub service_type: Option<ServiceType>,
+    pub region: Option<String>,
+    pub protocol: Option<Protocol>,
+    /// Only services flagged active on-chain. On by default.
+    pub active_only: bool,
+    /// Drop candidates with no endpoint that passed its health check.
+    pub healthy_only: bool,
+}
+
+impl Default for ServiceFilter {
+    fn default() -> Self {
+        Self { service_type: None, region: None, protocol: None, active_only: true, healthy_only: false }
+    }
+}
+
+impl ServiceFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_service_type(mut self, service_type: ServiceType) -> Self {
+        self.service_type = Some(service_type);
+        self
+    }
+
+    /// Match regions case-insensitively.
+    pub fn with_region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    pub fn with_protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    pub fn include_inactive(mut self) -> Self {
+        self.active_only = false;
+        self
+    }
+
+    pub fn healthy_only(mut self) -> Self {
+        self.healthy_only = true;
+        self
+    }
+
+    /// Whether a service passes the descriptor and active-flag filters.
+    /// Legacy entries have no descriptor, so they only pass filters without
+    /// descriptor criteria.
+    pub fn matches(&self, service: &NetworkService) -> bool {
+        if self.active_only && !service.is_active {
+            return false;
+        }
+        let Some(descriptor) = service.descriptor.as_ref().map(|signed| &signed.descriptor) else {
+            return self.service_type.is_none() && self.region.is_none() && self.protocol.is_none();
+        };
+        self.service_type.is_none_or(|t| descriptor.service_type == t)
+            && self.region.as_ref().is_none_or(|r| descriptor.region.eq_ignore_ascii_case(r))
+            && self.protocol.is_none_or(|p| descriptor.protocols().contains(&p))
+    }
+}
+
+/// The outcome of probing one endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Health {
+    Healthy { latency: Duration },
+    Unhealthy(String),
+    /// The endpoint's protocol can't be probed (UDP based protocols).
+    Unchecked,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeResult {
+    pub endpoint: Endpoint,
+    pub health: Health,
+}
+
+/// A discovered service with the health of its endpoints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub provider: Address,
+    pub service: NetworkService,
+    pub probes: Vec<ProbeResult>,
+    pub checked_at: u64,
+}
+
+impl Candidate {
+    /// Whether any endpoint passed its health check.
+    pub fn is_healthy(&self) -> bool {
+        self.probes.iter().any(|p| matches!(p.health, Health::Healthy { .. }))
+    }
+
+    /// The lowest latency across healthy endpoints.
+    pub fn best_latency(&self) -> Option<Duration> {
+        self.probes
+            .iter()
+            .filter_map(|p| match p.health {
+                Health::Healthy { latency } => Some(latency),
+                _ => None,
+            })
+            .min()
+    }
+}
+
+/// Order candidates best first: healthy before unhealthy, then by lowest
+/// latency, then by lowest price.
+pub fn rank_candidates(candidates: &mut [Candidate]) {
+    candidates.sort_by_key(|c| {
+        (
+            !c.is_healthy(),
+            c.best_latency().unwrap_or(Duration::MAX),
+            c.service.descriptor.as_ref().map_or(U256::MAX, |signed| signed.descriptor.pricing.amount),
+        )
+    });
+}
+
+/// Probes service endpoints.
+///
+/// TCP, WebSocket and HTTPS endpoints pass if a TCP connection opens; HTTP
+/// endpoints must also answer `GET <health_path>` with a 2xx or 3xx status.
+/// UDP, QUIC and WireGuard endpoints are reported as `Unchecked`.
+///
+/// Clones share one limit on how many probes are in flight at once.
+#[derive(Debug, Clone)]
+pub struct HealthChecker {
+    pub timeout: Duration,
+    pub health_path: String,
+    probe_limit: Arc<Semaphore>,
+}
+
+impl Default for HealthChecker {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_PROBE_TIMEOUT,
+            health_path: "/health".to_string(),
+            probe_limit: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_PROBES)),
+        }
+    }
+}
+
+impl HealthChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_health_path(mut self, path: impl Into<String>) -> Self {
+        self.health_path = path.into();
+        self
+    }
+
+    /// Cap how many endpoints are probed at once. Must be at least one.
+    pub fn with_max_concurrent_probes(mut self, max: usize) -> Self {
+        self.probe_limit = Arc::new(Semaphore::new(max.max(1)));
+        self
+    }
+
+    pub async fn probe(&self, endpoint: &Endpoint) -> ProbeResult {
+        let health = match endpoint.protocol {
+            Protocol::Udp | Protocol::Quic | Protocol::Wireguard => Health::Unchecked,
+            protocol => {
+                let _permit = self.probe_limit.acquire().await.expect("probe semaphore is never closed");
+                let started = Instant::now();
+                match tokio::time::timeout(self.timeout, self.probe_tcp(endpoint, protocol)).await {
+                    Ok(Ok(())) => Health::Healthy { latency: started.elapsed() },
+                    Ok(Err(reason)) => Health::Unhealthy(reason),
+                    Err(_) => Health::Unhealthy("timed out".to_string()),
+                }
+            }
+        };
+        ProbeResult { endpoint: endpoint.clone(), health }
+    }
+
+    async fn probe_tcp(&self, endpoint: &Endpoint, protocol: Protocol) -> Result<(), String> {
+        let mut stream = TcpStream::connect((endpoint.host.as_str(), endpoint.port))
+            .await
+            .map_err(|e| e.to_string())?;
+        if protocol != Protocol::Http {
+            return Ok(());
+        }
+
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            self.health_path, endpoint.host
+        );
+        stream.write_all(request.as_bytes()).await.map_err(|e| e.to_string())?;
+        let mut response = [0u8; 64];
+        let mut read = 0;
+        while read < 12 {
+            let n = stream.read(&mut response[read..]).await.map_err(|e| e.to_string())?;
+            if n == 0 {
+                break;
+            }
+            read += n;
+        }
+        let status_line = String::from_utf8_lossy(&response[..read]);
+        let status = status_line
+            .strip_prefix("HTTP/1.")
+            .and_then(|rest| rest.get(2..5))
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| "not an HTTP response".to_string())?;
+        if (200..400).contains(&status) {
+            Ok(())
+        } else {
+            Err(format!("HTTP status {}", status))
+        }
+    }
+
+    /// Probe every endpoint of a service concurrently, within the checker's
+    /// probe limit.
+    pub async fn check(&self, provider: Address, service: NetworkService) -> Candidate {
+        let mut probes = JoinSet::new();
+        let endpoints = service.descriptor.iter().flat_map(|signed| signed.descriptor.endpoints.iter().cloned());
+        for (index, endpoint) in endpoints.enumerate() {
+            let checker = self.clone();
+            probes.spawn(async move { (index, checker.probe(&endpoint).await) });
+        }
+        let mut results = Vec::new();
+        while let Some(result) = probes.join_next().await {
+            if let Ok(result) = result {
+                results.push(result);
+            }
+        }
+        results.sort_by_key(|(index, _)| *index);
+        Candidate {
+            provider,
+            service,
+            probes: results.into_iter().map(|(_, probe)| probe).collect(),
+            checked_at: unix_timestamp(),
+        }
+    }
+}
+
+struct DiscoveryCache {
+    refreshed_at: Instant,
+    /// The filter the candidates were selected with, `healthy_only` cleared.
+    filter: ServiceFilter,
+    candidates: Vec<Candidate>,
+}
+
+/// Finds providers in the `NetworkManager` registry and ranks them by health.
+///
+/// Matching services are probed and cached per filter for the refresh
+/// interval. Providers with a descriptor they didn't sign are skipped; legacy
+/// entries pass only filters without descriptor criteria.
+pub struct DiscoveryClient<M: Middleware> {
+    manager: Arc<NetworkManager<M>>,
+    checker: HealthChecker,
+    refresh_interval: Duration,
+    cache: Mutex<Option<DiscoveryCache>>,
+}
+
+impl<M: Middleware + 'static> DiscoveryClient<M> {
+    pub fn new(manager: Arc<NetworkManager<M>>) -> Self {
+        Self {
+            manager,
+            checker: HealthChecker::default(),
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            cache: Mutex::new(None),
+        }
+    }
+
+    pub fn with_health_checker(mut self, checker: HealthChecker) -> Self {
+        self.checker = checker;
+        self
+    }
+
+    pub fn with_refresh_interval(mut self, interval: Duration) -> Self {
+        self.refresh_interval = interval;
+        self
+    }
+
+    pub fn manager(&self) -> &NetworkManager<M> {
+        &self.manager
+    }
+
+    /// Load every provider's verified service from the registry, fetching
+    /// their services in batches.
+    pub async fn load_services(&self) -> Result<Vec<(Address, NetworkService)>, NetworkError<M>> {
+        let providers = self.manager.get_service_providers().await?;
+        let mut services = Vec::new();
+        for (provider, service) in self.manager.get_network_services(&providers).await? {
+            match service {
+                Ok(service) => services.push((provider, service)),
+                Err(e @ NetworkError::Descriptor(_)) | Err(e @ NetworkError::Contract(ContractError::Revert(_))) => {
+                    log::warn!("skipping provider {:?}: {}", provider, e)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(services)
+    }
+
+    /// Reload the services matching `filter` and re-probe them, replacing
+    /// the cache. Services the filter rejects are never probed.
+    pub async fn refresh(&self, filter: &ServiceFilter) -> Result<Vec<Candidate>, NetworkError<M>> {
+        let filter = ServiceFilter { healthy_only: false, ..filter.clone() };
+        let services = self.load_services().await?;
+        let mut checks = JoinSet::new();
+        for (provider, service) in services.into_iter().filter(|(_, service)| filter.matches(service)) {
+            let checker = self.checker.clone();
+            checks.spawn(async move { checker.check(provider, service).await });
+        }
+        let mut candidates = Vec::new();
+        while let Some(candidate) = checks.join_next().await {
+            if let Ok(candidate) = candidate {
+                candidates.push(candidate);
+            }
+        }
+
+        *self.cache.lock().await = Some(DiscoveryCache { refreshed_at: Instant::now(), filter, candidates: candidates.clone() });
+        Ok(candidates)
+    }
+
+    /// Drop the cache so the next `discover` reloads from the chain.
+    pub async fn invalidate(&self) {
+        *self.cache.lock().await = None;
+    }
+
+    /// Ranked candidates matching `filter`, refreshing the cache if it is
+    /// older than the refresh interval or was built for another filter.
+    pub async fn discover(&self, filter: &ServiceFilter) -> Result<Vec<Candidate>, NetworkError<M>> {
+        let cached = {
+            let cache = self.cache.lock().await;
+            cache
+                .as_ref()
+                .filter(|cache| cache.refreshed_at.elapsed() < self.refresh_interval)
+                .filter(|cache| cache.filter == ServiceFilter { healthy_only: false, ..filter.clone() })
+                .map(|cache| cache.candidates.clone())
+        };
+        let candidates = match cached {
+            Some(candidates) => candidates,
+            None => self.refresh(filter).await?,
+        };
+
+        let mut matching: Vec<Candidate> = candidates
+            .into_iter()
+            .filter(|c| filter.matches(&c.service) && (!filter.healthy_only || c.is_healthy()))
+            .collect();
+        rank_candidates(&mut matching);
+        Ok(matching)
+    }
+}
@@ -0,0 +1,53 @@
+// src/net/error.rs
+
+use ethers::prelude::*;
+use std::fmt;
+
+use super::descriptor::DescriptorError;
+
+/// Errors returned by `NetworkManager` calls that go beyond the raw contract.
+#[derive(Debug)]
+pub enum NetworkError<M: Middleware> {
+    /// The underlying contract call or transaction failed.
+    Contract(ContractError<M>),
+    /// A service descriptor failed validation or signature checks.
+    Descriptor(DescriptorError),
+    /// A batched lookup through Multicall3 failed as a whole.
+    Multicall(MulticallError<M>),
+    /// The transaction was sent but dropped before it was mined.
+    Dropped(TxHash),
+}
+
+impl<M: Middleware> fmt::Display for NetworkError<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::Contract(e) => write!(f, "contract error: {}", e),
+            NetworkError::Descriptor(e) => write!(f, "{}", e),
+            NetworkError::Multicall(e) => write!(f, "multicall error: {}", e),
+            NetworkError::Dropped(tx_hash) => write!(f, "transaction {:?} was dropped before it was mined", tx_hash),
+        }
+    }
+}
+
+impl<M: Middleware> std::error::Error for NetworkError<M> {}
+
+impl<M: Middleware> From<ContractError<M>> for NetworkError<M> {
+    fn from(e: ContractError<M>) -> Self {
+        NetworkError::Contract(e)
+    }
+}
+
+impl<M: Middleware> From<DescriptorError> for NetworkError<M> {
+    fn from(e: DescriptorError) -> Self {
+        NetworkError::Descriptor(e)
+    }
+}
+
+impl<M: Middleware> From<MulticallError<M>> for NetworkError<M> {
+    fn from(e: MulticallError<M>) -> Self {
+        match e {
+            MulticallError::ContractError(e) => NetworkError::Contract(e),
+            e => NetworkError::Multicall(e),
+        }
+    }
+}
@@ -1,8 +1,15 @@
 // src/network/manager.rs
 
+use ethers::abi::Tokenizable;
 use ethers::prelude::*;
 use std::sync::Arc;
 
+use super::descriptor::{DescriptorError, SignedServiceDescriptor};
+use super::error::NetworkError;
+
+/// How many `getNetworkService` calls go into one multicall.
+pub const MULTICALL_BATCH_SIZE: usize = 100;
+
 abigen!(
     NetworkManagerContract,
     r#"[
@@ -17,55 +24,193 @@ abigen!(
 
 pub struct NetworkManager<M: Middleware> {
     contract: NetworkManagerContract<M>,
-    client: Arc<M>,
+    multicall_address: Option<Address>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct NetworkService {
     pub owner: Address,
+    /// The raw `serviceDetails` string as stored on-chain.
     pub service_details: String,
+    /// The parsed descriptor, verified against the provider's signature.
+    /// `None` for legacy entries whose details are free-form text written
+    /// before descriptors were introduced.
+    pub descriptor: Option<SignedServiceDescriptor>,
     pub is_active: bool,
 }
 
+impl NetworkService {
+    /// Details that don't parse as a signed descriptor are kept as a legacy
+    /// entry. Details that do parse must verify against `provider`.
+    fn from_parts(provider: Address, owner: Address, service_details: String, is_active: bool) -> Result<Self, DescriptorError> {
+        let descriptor = match SignedServiceDescriptor::from_details(&service_details) {
+            Ok(descriptor) => {
+                descriptor.verify_for(provider)?;
+                Some(descriptor)
+            }
+            Err(DescriptorError::Format(_)) => None,
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            owner,
+            service_details,
+            descriptor,
+            is_active,
+        })
+    }
+
+    /// Whether the entry predates signed descriptors. Migrate it by writing
+    /// a signed descriptor with `update_network_service`.
+    pub fn is_legacy(&self) -> bool {
+        self.descriptor.is_none()
+    }
+}
+
+/// One page of the provider registry, sliced from `getServiceProviders`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderPage {
+    pub providers: Vec<Address>,
+    pub offset: u64,
+    /// Number of registered providers when the page was read.
+    pub total: u64,
+}
+
+impl ProviderPage {
+    /// The offset of the following page, or `None` if this is the last one.
+    pub fn next_offset(&self) -> Option<u64> {
+        let next = self.offset + self.providers.len() as u64;
+        (!self.providers.is_empty() && next < self.total).then_some(next)
+    }
+}
+
 impl<M: Middleware + 'static> NetworkManager<M> {
     
     pub fn new(address: Address, client: Arc<M>) -> Self {
-        let contract = NetworkManagerContract::new(address, Arc::clone(&client));
-        Self { contract, client }
+        let contract = NetworkManagerContract::new(address, client);
+        Self { contract, multicall_address: None }
+    }
+
+    /// Use the Multicall3 contract at `address` for batched lookups instead
+    /// of the canonical deployment. Needed on local and private chains.
+    pub fn with_multicall_address(mut self, address: Address) -> Self {
+        self.multicall_address = Some(address);
+        self
+    }
+
+    pub fn address(&self) -> Address {
+        self.contract.address()
     }
 
-    pub async fn add_network_service(&self, provider: Address, service_details: String) -> Result<TransactionReceipt, ContractError<M>> {
-        let tx = self.contract.add_network_service(provider, service_details);
-        let pending_tx = tx.send().await?;
-        Ok(pending_tx.await?.expect("Transaction failed"))
+    /// Register a provider's service. The descriptor must be valid and signed
+    /// by `provider`.
+    pub async fn add_network_service(&self, provider: Address, descriptor: &SignedServiceDescriptor) -> Result<TransactionReceipt, NetworkError<M>> {
+        descriptor.verify_for(provider)?;
+        let tx = self.contract.add_network_service(provider, descriptor.to_details()?);
+        let receipt = mined(tx.send().await?).await?;
+        Ok(receipt)
     }
 
-    pub async fn get_network_service(&self, provider: Address) -> Result<NetworkService, ContractError<M>> {
+    /// Fetch a provider's service, parsing and verifying its descriptor.
+    /// Legacy free-form entries are returned with no descriptor rather than
+    /// as an error.
+    pub async fn get_network_service(&self, provider: Address) -> Result<NetworkService, NetworkError<M>> {
         let (owner, service_details, is_active) = self.contract.get_network_service(provider).call().await?;
-        Ok(NetworkService {
-            owner,
-            service_details,
-            is_active,
-        })
+        Ok(NetworkService::from_parts(provider, owner, service_details, is_active)?)
     }
 
-    pub async fn update_network_service(&self, provider: Address, new_service_details: String) -> Result<TransactionReceipt, ContractError<M>> {
-        let tx = self.contract.update_network_service(provider, new_service_details);
-        let pending_tx = tx.send().await?;
-        Ok(pending_tx.await?.expect("Transaction failed"))
+    /// Fetch many providers' services through Multicall3, in batches of
+    /// `MULTICALL_BATCH_SIZE`. Results are in the order of `providers`; a
+    /// provider whose lookup reverts or whose descriptor fails verification
+    /// gets its own error without failing the batch.
+    ///
+    /// Falls back to one call per provider when no multicall address was set
+    /// and the chain has no canonical Multicall3 deployment.
+    pub async fn get_network_services(
+        &self,
+        providers: &[Address],
+    ) -> Result<Vec<(Address, Result<NetworkService, NetworkError<M>>)>, NetworkError<M>> {
+        if providers.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut multicall = match Multicall::new(self.contract.client(), self.multicall_address).await {
+            Ok(multicall) => multicall,
+            Err(MulticallError::InvalidChainId(_)) => {
+                let mut services = Vec::with_capacity(providers.len());
+                for &provider in providers {
+                    services.push((provider, self.get_network_service(provider).await));
+                }
+                return Ok(services);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut services = Vec::with_capacity(providers.len());
+        for batch in providers.chunks(MULTICALL_BATCH_SIZE) {
+            multicall.clear_calls();
+            for &provider in batch {
+                multicall.add_call(self.contract.get_network_service(provider), true);
+            }
+            for (&provider, result) in batch.iter().zip(multicall.call_raw().await?) {
+                let service = match result {
+                    Ok(token) => <(Address, String, bool)>::from_token(token)
+                        .map_err(|e| NetworkError::Contract(ContractError::DetokenizationError(e)))
+                        .and_then(|(owner, service_details, is_active)| {
+                            Ok(NetworkService::from_parts(provider, owner, service_details, is_active)?)
+                        }),
+                    Err(revert) => Err(NetworkError::Contract(ContractError::Revert(revert))),
+                };
+                services.push((provider, service));
+            }
+        }
+        Ok(services)
     }
 
-    pub async fn remove_network_service(&self, provider: Address) -> Result<TransactionReceipt, ContractError<M>> {
+    /// The raw `serviceDetails` string, without parsing it.
+    pub async fn get_service_details(&self, provider: Address) -> Result<String, ContractError<M>> {
+        let (_, service_details, _) = self.contract.get_network_service(provider).call().await?;
+        Ok(service_details)
+    }
+
+    /// Replace a provider's descriptor. The new descriptor must be valid and
+    /// signed by `provider`.
+    pub async fn update_network_service(&self, provider: Address, descriptor: &SignedServiceDescriptor) -> Result<TransactionReceipt, NetworkError<M>> {
+        descriptor.verify_for(provider)?;
+        let tx = self.contract.update_network_service(provider, descriptor.to_details()?);
+        let receipt = mined(tx.send().await?).await?;
+        Ok(receipt)
+    }
+
+    pub async fn remove_network_service(&self, provider: Address) -> Result<TransactionReceipt, NetworkError<M>> {
         let tx = self.contract.remove_network_service(provider);
-        let pending_tx = tx.send().await?;
-        Ok(pending_tx.await?.expect("Transaction failed"))
+        let receipt = mined(tx.send().await?).await?;
+        Ok(receipt)
     }
 
     pub async fn is_service_provider(&self, provider: Address) -> Result<bool, ContractError<M>> {
         self.contract.is_service_provider(provider).call().await
     }
 
+    /// Every registered provider in a single call. The contract has no paged
+    /// view, so this is the only way to list the registry.
     pub async fn get_service_providers(&self) -> Result<Vec<Address>, ContractError<M>> {
         self.contract.get_service_providers().call().await
     }
-}
\ No newline at end of file
+
+    /// Up to `limit` providers starting at `offset`.
+    ///
+    /// `NetworkManager` has no paged view, so the full list is fetched and
+    /// sliced client-side. This pages results for display; it does not
+    /// reduce what the node has to return.
+    pub async fn get_service_providers_page(&self, offset: u64, limit: u64) -> Result<ProviderPage, ContractError<M>> {
+        let all = self.get_service_providers().await?;
+        let providers = all.iter().skip(offset as usize).take(limit as usize).copied().collect();
+        Ok(ProviderPage { providers, offset, total: all.len() as u64 })
+    }
+}
+
+/// Wait for a sent transaction's receipt, failing with `Dropped` if the
+/// transaction left the mempool without being mined.
+async fn mined<M: Middleware>(pending_tx: PendingTransaction<'_, M::Provider>) -> Result<TransactionReceipt, NetworkError<M>> {
+    let tx_hash = *pending_tx;
+    pending_tx.await.map_err(ContractError::from)?.ok_or(NetworkError::Dropped(tx_hash))
+}
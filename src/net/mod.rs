@@ -1,3 +1,21 @@
+mod agent;
+mod descriptor;
+mod discovery;
+mod error;
 mod manager;
+mod selection;
 
-pub use manager::NetworkManager;
\ No newline at end of file
+pub use agent::{
+    descriptor_hash, ChannelSink, Heartbeat, HeartbeatSink, ProviderAgent, Registration, SignedHeartbeat, DEFAULT_HEARTBEAT_INTERVAL,
+};
+pub use descriptor::{
+    Capacity, DescriptorError, Endpoint, Pricing, PricingUnit, Protocol, ServiceDescriptor, ServiceType,
+    SignedServiceDescriptor, DESCRIPTOR_SCHEMA_VERSION, MAX_DESCRIPTOR_LEN,
+};
+pub use discovery::{
+    rank_candidates, Candidate, DiscoveryClient, Health, HealthChecker, ProbeResult, ServiceFilter,
+    DEFAULT_PROBE_TIMEOUT, DEFAULT_REFRESH_INTERVAL,
+};
+pub use error::NetworkError;
+pub use manager::{NetworkManager, NetworkService, ProviderPage, MULTICALL_BATCH_SIZE};
+pub use selection::{ProviderSelector, ScoredProvider, Selection, SelectionPolicy, SelectionReason};
@@ -0,0 +1,212 @@
+// src/net/selection.rs
+
+use ethers::core::rand::{thread_rng, Rng};
+use ethers::prelude::*;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::reputation::ReputationManager;
+
+use super::discovery::ServiceFilter;
+use super::error::NetworkError;
+use super::manager::{NetworkManager, NetworkService};
+
+/// How `ProviderSelector::select` chooses among scored providers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionPolicy {
+    /// Pick one provider at random with probability proportional to its
+    /// score. If every score is zero the pick is uniform.
+    WeightedRandom,
+    /// The `k` highest scoring providers.
+    TopK(usize),
+    /// Every provider whose score reaches the threshold, best first.
+    MinThreshold(U256),
+}
+
+/// A provider's listing together with its reputation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredProvider {
+    pub provider: Address,
+    pub service: NetworkService,
+    /// The producer score from `getCompleteProfile`. Zero for providers
+    /// without a reputation profile.
+    pub producer_score: U256,
+    /// The score for the selector's product, if one was set.
+    pub product_score: Option<U256>,
+    /// `producer_score + product_weight * product_score`.
+    pub score: U256,
+}
+
+/// Why a provider was selected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectionReason {
+    pub policy: SelectionPolicy,
+    /// 1-based position among eligible providers by score.
+    pub rank: usize,
+    /// How many providers the policy chose from.
+    pub eligible: usize,
+    pub producer_score: U256,
+    pub product_score: Option<U256>,
+    pub score: U256,
+    /// The chance of this pick under `WeightedRandom`.
+    pub probability: Option<f64>,
+}
+
+impl fmt::Display for SelectionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ranked {} of {} with score {} (producer {}", self.rank, self.eligible, self.score, self.producer_score)?;
+        if let Some(product) = self.product_score {
+            write!(f, ", product {}", product)?;
+        }
+        write!(f, ")")?;
+        match self.policy {
+            SelectionPolicy::WeightedRandom => {
+                write!(f, ", drawn with probability {:.3}", self.probability.unwrap_or_default())
+            }
+            SelectionPolicy::TopK(k) => write!(f, ", within the top {}", k),
+            SelectionPolicy::MinThreshold(threshold) => write!(f, ", at or above threshold {}", threshold),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub provider: ScoredProvider,
+    pub reason: SelectionReason,
+}
+
+fn u256_to_f64(value: U256) -> f64 {
+    if value.bits() > 128 {
+        f64::MAX
+    } else {
+        value.as_u128() as f64
+    }
+}
+
+impl SelectionPolicy {
+    /// Apply the policy to already scored providers.
+    pub fn apply<R: Rng + ?Sized>(&self, mut scored: Vec<ScoredProvider>, rng: &mut R) -> Vec<Selection> {
+        scored.sort_by_key(|p| std::cmp::Reverse(p.score));
+        let eligible = scored.len();
+        let reason = |provider: &ScoredProvider, rank: usize, eligible: usize, probability: Option<f64>| SelectionReason {
+            policy: *self,
+            rank,
+            eligible,
+            producer_score: provider.producer_score,
+            product_score: provider.product_score,
+            score: provider.score,
+            probability,
+        };
+
+        match *self {
+            SelectionPolicy::TopK(k) => scored
+                .into_iter()
+                .take(k)
+                .enumerate()
+                .map(|(i, provider)| Selection { reason: reason(&provider, i + 1, eligible, None), provider })
+                .collect(),
+            SelectionPolicy::MinThreshold(threshold) => {
+                scored.retain(|p| p.score >= threshold);
+                let eligible = scored.len();
+                scored
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, provider)| Selection { reason: reason(&provider, i + 1, eligible, None), provider })
+                    .collect()
+            }
+            SelectionPolicy::WeightedRandom => {
+                if scored.is_empty() {
+                    return Vec::new();
+                }
+                let weights: Vec<f64> = scored.iter().map(|p| u256_to_f64(p.score)).collect();
+                let total: f64 = weights.iter().sum();
+                let (index, probability) = if total > 0.0 {
+                    let mut target = rng.gen::<f64>() * total;
+                    let index = weights
+                        .iter()
+                        .position(|w| {
+                            target -= w;
+                            target < 0.0
+                        })
+                        .unwrap_or(eligible - 1);
+                    (index, weights[index] / total)
+                } else {
+                    (rng.gen_range(0..eligible), 1.0 / eligible as f64)
+                };
+                let provider = scored.swap_remove(index);
+                vec![Selection { reason: reason(&provider, index + 1, eligible, Some(probability)), provider }]
+            }
+        }
+    }
+}
+
+/// Chooses service providers by combining their `NetworkManager` listing with
+/// their `ReputationManager` scores.
+pub struct ProviderSelector<M: Middleware> {
+    network: Arc<NetworkManager<M>>,
+    reputation: Arc<ReputationManager<M>>,
+    product: Option<[u8; 32]>,
+    product_weight: u64,
+}
+
+impl<M: Middleware + 'static> ProviderSelector<M> {
+    pub fn new(network: Arc<NetworkManager<M>>, reputation: Arc<ReputationManager<M>>) -> Self {
+        Self { network, reputation, product: None, product_weight: 1 }
+    }
+
+    /// Also score providers on `product_hash`, weighting the product score by
+    /// `weight` relative to the producer score.
+    pub fn with_product(mut self, product_hash: [u8; 32], weight: u64) -> Self {
+        self.product = Some(product_hash);
+        self.product_weight = weight;
+        self
+    }
+
+    async fn reputation_of(&self, provider: Address) -> Result<(U256, Option<U256>), NetworkError<M>> {
+        let producer_score = match self.reputation.get_complete_profile(provider).await {
+            Ok((_, producer_score, _)) => producer_score,
+            Err(ContractError::Revert(_)) => U256::zero(),
+            Err(e) => return Err(e.into()),
+        };
+        let product_score = match self.product {
+            Some(product_hash) => match self.reputation.get_product_score(provider, product_hash).await {
+                Ok(score) => Some(score),
+                Err(ContractError::Revert(_)) => Some(U256::zero()),
+                Err(e) => return Err(e.into()),
+            },
+            None => None,
+        };
+        Ok((producer_score, product_score))
+    }
+
+    /// Score every listed provider matching `filter`. Providers with invalid
+    /// descriptors, or that left the registry mid-load, are skipped.
+    pub async fn score_providers(&self, filter: &ServiceFilter) -> Result<Vec<ScoredProvider>, NetworkError<M>> {
+        let providers = self.network.get_service_providers().await?;
+        let mut scored = Vec::new();
+        for (provider, service) in self.network.get_network_services(&providers).await? {
+            let service = match service {
+                Ok(service) => service,
+                Err(e @ NetworkError::Descriptor(_)) | Err(e @ NetworkError::Contract(ContractError::Revert(_))) => {
+                    log::warn!("skipping provider {:?}: {}", provider, e);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            if !filter.matches(&service) {
+                continue;
+            }
+            let (producer_score, product_score) = self.reputation_of(provider).await?;
+            let score = producer_score
+                .saturating_add(product_score.unwrap_or_default().saturating_mul(U256::from(self.product_weight)));
+            scored.push(ScoredProvider { provider, service, producer_score, product_score, score });
+        }
+        Ok(scored)
+    }
+
+    /// Score matching providers and apply `policy`.
+    pub async fn select(&self, filter: &ServiceFilter, policy: SelectionPolicy) -> Result<Vec<Selection>, NetworkError<M>> {
+        let scored = self.score_providers(filter).await?;
+        Ok(policy.apply(scored, &mut thread_rng()))
+    }
+}
@@ -0,0 +1,272 @@
+// src/reputation/actions.rs
+
+use ethers::types::U256;
+use ethers::utils::keccak256;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+/// Action names the SDK uses by convention. The contracts don't define an
+/// action registry, so these carry no built-in on-chain weight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KnownAction {
+    /// A provider delivered a service to a consumer.
+    ServiceDelivery,
+    /// A consumer paid for a service.
+    ServicePayment,
+    EscrowRelease,
+    EscrowRefund,
+    Dispute,
+    /// A provider passed or failed a liveness check.
+    Uptime,
+    ProductReview,
+    IdentityVerification,
+}
+
+impl KnownAction {
+    pub const ALL: [KnownAction; 8] = [
+        KnownAction::ServiceDelivery,
+        KnownAction::ServicePayment,
+        KnownAction::EscrowRelease,
+        KnownAction::EscrowRefund,
+        KnownAction::Dispute,
+        KnownAction::Uptime,
+        KnownAction::ProductReview,
+        KnownAction::IdentityVerification,
+    ];
+
+    /// The name hashed into the on-chain action type.
+    pub fn name(&self) -> &'static str {
+        match self {
+            KnownAction::ServiceDelivery => "SERVICE_DELIVERY",
+            KnownAction::ServicePayment => "SERVICE_PAYMENT",
+            KnownAction::EscrowRelease => "ESCROW_RELEASE",
+            KnownAction::EscrowRefund => "ESCROW_REFUND",
+            KnownAction::Dispute => "DISPUTE",
+            KnownAction::Uptime => "UPTIME",
+            KnownAction::ProductReview => "PRODUCT_REVIEW",
+            KnownAction::IdentityVerification => "IDENTITY_VERIFICATION",
+        }
+    }
+
+    pub fn action_type(&self) -> ActionType {
+        ActionType::from_name(self.name())
+    }
+}
+
+/// A reputation action: a name and its `bytes32` id, `keccak256(name)`, the
+/// same derivation as Solidity's `keccak256(bytes("NAME"))`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ActionType {
+    name: String,
+    id: [u8; 32],
+}
+
+impl ActionType {
+    pub fn from_name(name: impl Into<String>) -> Self {
+        let name = name.into();
+        let id = keccak256(name.as_bytes());
+        Self { name, id }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn id(&self) -> [u8; 32] {
+        self.id
+    }
+}
+
+impl fmt::Display for ActionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl From<KnownAction> for ActionType {
+    fn from(action: KnownAction) -> Self {
+        action.action_type()
+    }
+}
+
+impl From<ActionType> for [u8; 32] {
+    fn from(action: ActionType) -> Self {
+        action.id
+    }
+}
+
+impl From<&ActionType> for [u8; 32] {
+    fn from(action: &ActionType) -> Self {
+        action.id
+    }
+}
+
+impl From<KnownAction> for [u8; 32] {
+    fn from(action: KnownAction) -> Self {
+        action.action_type().id
+    }
+}
+
+/// Maps action ids seen on-chain back to names.
+#[derive(Debug, Clone)]
+pub struct ActionRegistry {
+    by_id: HashMap<[u8; 32], ActionType>,
+}
+
+/// A registry of the `KnownAction`s.
+impl Default for ActionRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        for action in KnownAction::ALL {
+            registry.register(action.name());
+        }
+        registry
+    }
+}
+
+impl ActionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry without the known actions.
+    pub fn empty() -> Self {
+        Self { by_id: HashMap::new() }
+    }
+
+    /// Add an application-specific action. Registering a name twice is a no-op.
+    pub fn register(&mut self, name: impl Into<String>) -> ActionType {
+        let action = ActionType::from_name(name);
+        self.by_id.entry(action.id).or_insert(action).clone()
+    }
+
+    pub fn with_action(mut self, name: impl Into<String>) -> Self {
+        self.register(name);
+        self
+    }
+
+    /// The registered action named `name`.
+    pub fn get(&self, name: &str) -> Option<&ActionType> {
+        self.by_id.get(&keccak256(name.as_bytes()))
+    }
+
+    /// The registered action with on-chain id `id`.
+    pub fn lookup(&self, id: &[u8; 32]) -> Option<&ActionType> {
+        self.by_id.get(id)
+    }
+
+    /// The action's name, or its id in hex if it isn't registered.
+    pub fn describe(&self, id: &[u8; 32]) -> String {
+        match self.lookup(id) {
+            Some(action) => action.name.clone(),
+            None => format!("0x{}", hex::encode(id)),
+        }
+    }
+
+    /// Registered actions sorted by name.
+    pub fn actions(&self) -> Vec<&ActionType> {
+        let mut actions: Vec<&ActionType> = self.by_id.values().collect();
+        actions.sort_by(|a, b| a.name.cmp(&b.name));
+        actions
+    }
+}
+
+/// Per-action weights, stored as a JSON object from action name to weight:
+///
+/// ```json
+/// { "SERVICE_DELIVERY": 10, "DISPUTE": "250000000000000000000" }
+/// ```
+///
+/// Weights may be JSON integers or decimal strings, for values past `u64`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ActionWeights {
+    #[serde(serialize_with = "serialize_weights", deserialize_with = "deserialize_weights")]
+    weights: BTreeMap<String, U256>,
+}
+
+impl ActionWeights {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_weight(mut self, name: impl Into<String>, weight: impl Into<U256>) -> Self {
+        self.weights.insert(name.into(), weight.into());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<U256> {
+        self.weights.get(name).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.weights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weights.is_empty()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let data = std::fs::read(path)?;
+        serde_json::from_slice(&data).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    /// Write to a temporary file beside `path`, then rename it into place, so
+    /// a crash mid-write never leaves a truncated weights file.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(tmp, path)
+    }
+
+    /// Pair each weight with its registered action, sorted by name. Names
+    /// the registry doesn't know are returned as the error, so a typo can't
+    /// silently weight an action nobody records.
+    pub fn resolve(&self, registry: &ActionRegistry) -> Result<Vec<(ActionType, U256)>, Vec<String>> {
+        let mut resolved = Vec::with_capacity(self.weights.len());
+        let mut unknown = Vec::new();
+        for (name, weight) in &self.weights {
+            match registry.get(name) {
+                Some(action) => resolved.push((action.clone(), *weight)),
+                None => unknown.push(name.clone()),
+            }
+        }
+        if unknown.is_empty() {
+            Ok(resolved)
+        } else {
+            Err(unknown)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WeightValue {
+    Integer(u64),
+    Decimal(String),
+}
+
+fn serialize_weights<S: Serializer>(weights: &BTreeMap<String, U256>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(weights.iter().map(|(name, weight)| (name, weight.to_string())))
+}
+
+fn deserialize_weights<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<String, U256>, D::Error> {
+    let raw = BTreeMap::<String, WeightValue>::deserialize(deserializer)?;
+    raw.into_iter()
+        .map(|(name, value)| {
+            let weight = match value {
+                WeightValue::Integer(weight) => U256::from(weight),
+                WeightValue::Decimal(weight) => U256::from_dec_str(&weight)
+                    .map_err(|_| serde::de::Error::custom(format!("invalid weight '{}' for {}", weight, name)))?,
+            };
+            Ok((name, weight))
+        })
+        .collect()
+}
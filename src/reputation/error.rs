@@ -0,0 +1,58 @@
+// src/reputation/error.rs
+
+use ethers::prelude::*;
+use std::fmt;
+
+use super::escrow::EscrowError;
+
+/// Errors returned by the reputation workflows built on top of the raw
+/// `ReputationManager` contract calls.
+#[derive(Debug)]
+pub enum ReputationError<M: Middleware> {
+    /// The underlying contract call or transaction failed.
+    Contract(ContractError<M>),
+    /// Weights were configured for actions the registry doesn't know.
+    UnknownActions(Vec<String>),
+    /// The escrow isn't in a state that allows the requested call.
+    Escrow(EscrowError),
+    /// The wallet holds less of an ERC-20 token than it tried to escrow.
+    InsufficientBalance { token: Address, required: U256, available: U256 },
+    /// The wallet doesn't own the ERC-721 token it tried to escrow.
+    NotTokenOwner { token: Address, token_id: U256, owner: Address },
+    /// A permit or other message could not be signed.
+    Signing(String),
+    /// The transaction was sent but dropped before it was mined.
+    Dropped(TxHash),
+}
+
+impl<M: Middleware> fmt::Display for ReputationError<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReputationError::Contract(e) => write!(f, "contract error: {}", e),
+            ReputationError::UnknownActions(names) => write!(f, "unknown action types: {}", names.join(", ")),
+            ReputationError::Escrow(e) => write!(f, "{}", e),
+            ReputationError::InsufficientBalance { token, required, available } => {
+                write!(f, "balance of token {:?} is {}, need {}", token, available, required)
+            }
+            ReputationError::NotTokenOwner { token, token_id, owner } => {
+                write!(f, "token {} of {:?} is owned by {:?}", token_id, token, owner)
+            }
+            ReputationError::Signing(msg) => write!(f, "signing failed: {}", msg),
+            ReputationError::Dropped(tx_hash) => write!(f, "transaction {:?} was dropped before it was mined", tx_hash),
+        }
+    }
+}
+
+impl<M: Middleware> std::error::Error for ReputationError<M> {}
+
+impl<M: Middleware> From<ContractError<M>> for ReputationError<M> {
+    fn from(e: ContractError<M>) -> Self {
+        ReputationError::Contract(e)
+    }
+}
+
+impl<M: Middleware> From<EscrowError> for ReputationError<M> {
+    fn from(e: EscrowError) -> Self {
+        ReputationError::Escrow(e)
+    }
+}
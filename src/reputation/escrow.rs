@@ -0,0 +1,282 @@
+// src/reputation/escrow.rs
+
+use ethers::abi::AbiDecode;
+use ethers::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::manager::ReputationManagerContractCalls;
+
+/// Which escrow contract holds the asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EscrowKind {
+    Native,
+    Erc20,
+    Erc721,
+}
+
+/// What is held in escrow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscrowAsset {
+    /// Wei sent with `initiateEscrow`.
+    Native { amount: U256 },
+    /// Tokens pulled from the depositor by `initiateERC20Escrow`.
+    Erc20 { amount: U256 },
+    /// An NFT pulled from the depositor. `initiateERC721Escrow()` takes no
+    /// token id, so it is only known for escrows the SDK opened; escrows
+    /// decoded from chain data have `None`.
+    Erc721 { token_id: Option<U256> },
+}
+
+impl EscrowAsset {
+    pub fn kind(&self) -> EscrowKind {
+        match self {
+            EscrowAsset::Native { .. } => EscrowKind::Native,
+            EscrowAsset::Erc20 { .. } => EscrowKind::Erc20,
+            EscrowAsset::Erc721 { .. } => EscrowKind::Erc721,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EscrowState {
+    /// The initiating transaction was sent but hasn't been confirmed.
+    Initiated,
+    /// The escrow contract holds the asset.
+    Funded,
+    /// Paid out to the counterparty. Final.
+    Released,
+    /// Returned to the depositor. Final.
+    Refunded,
+    /// Flagged for resolution. The contract has no dispute call, so this is
+    /// tracked by the SDK only; the dispute is resolved by releasing or
+    /// refunding the escrow.
+    Disputed,
+}
+
+impl EscrowState {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, EscrowState::Released | EscrowState::Refunded)
+    }
+
+    /// Whether an escrow in this state may move to `to`.
+    pub fn can_transition(&self, to: EscrowState) -> bool {
+        use EscrowState::*;
+        matches!(
+            (self, to),
+            (Initiated, Funded) | (Funded, Released | Refunded | Disputed) | (Disputed, Released | Refunded)
+        )
+    }
+}
+
+impl fmt::Display for EscrowState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EscrowState::Initiated => "initiated",
+            EscrowState::Funded => "funded",
+            EscrowState::Released => "released",
+            EscrowState::Refunded => "refunded",
+            EscrowState::Disputed => "disputed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EscrowError {
+    InvalidTransition { from: EscrowState, to: EscrowState },
+}
+
+impl fmt::Display for EscrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EscrowError::InvalidTransition { from, to } => write!(f, "escrow can't move from {} to {}", from, to),
+        }
+    }
+}
+
+impl std::error::Error for EscrowError {}
+
+/// One step in an escrow's lifecycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscrowTransition {
+    pub state: EscrowState,
+    /// The transaction that caused it; `None` for SDK-side transitions.
+    pub transaction_hash: Option<H256>,
+    pub block_number: Option<u64>,
+    /// The block timestamp, when the transition was read from a block.
+    pub timestamp: Option<u64>,
+}
+
+/// A single escrow and the transitions it went through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Escrow {
+    pub depositor: Address,
+    pub asset: EscrowAsset,
+    state: EscrowState,
+    transitions: Vec<EscrowTransition>,
+}
+
+impl Escrow {
+    /// A new escrow in the `Initiated` state.
+    pub fn new(depositor: Address, asset: EscrowAsset) -> Self {
+        Self {
+            depositor,
+            asset,
+            state: EscrowState::Initiated,
+            transitions: vec![EscrowTransition {
+                state: EscrowState::Initiated,
+                transaction_hash: None,
+                block_number: None,
+                timestamp: None,
+            }],
+        }
+    }
+
+    pub fn kind(&self) -> EscrowKind {
+        self.asset.kind()
+    }
+
+    pub fn state(&self) -> EscrowState {
+        self.state
+    }
+
+    pub fn transitions(&self) -> &[EscrowTransition] {
+        &self.transitions
+    }
+
+    /// Not yet released or refunded.
+    pub fn is_active(&self) -> bool {
+        !self.state.is_terminal()
+    }
+
+    /// Fail unless the escrow may move to `to`.
+    pub fn check(&self, to: EscrowState) -> Result<(), EscrowError> {
+        if self.state.can_transition(to) {
+            Ok(())
+        } else {
+            Err(EscrowError::InvalidTransition { from: self.state, to })
+        }
+    }
+
+    pub fn transition(&mut self, to: EscrowState, transaction_hash: Option<H256>, block_number: Option<u64>) -> Result<(), EscrowError> {
+        self.check(to)?;
+        self.state = to;
+        self.transitions.push(EscrowTransition { state: to, transaction_hash, block_number, timestamp: None });
+        Ok(())
+    }
+
+    /// The block timestamp of the funding transaction, if it was indexed.
+    pub fn funded_at(&self) -> Option<u64> {
+        self.transitions.iter().find(|t| t.state == EscrowState::Funded).and_then(|t| t.timestamp)
+    }
+
+    /// Flag a funded escrow as disputed.
+    pub fn dispute(&mut self) -> Result<(), EscrowError> {
+        self.transition(EscrowState::Disputed, None, None)
+    }
+}
+
+/// An escrow call made to the `ReputationManager`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscrowCall {
+    Initiate(EscrowAsset),
+    Release(EscrowKind),
+    Refund(EscrowKind),
+}
+
+/// Decode an escrow call from transaction input. `value` is the wei sent
+/// with the transaction, which is the amount of a native escrow.
+pub fn decode_escrow_call(input: &[u8], value: U256) -> Option<EscrowCall> {
+    use ReputationManagerContractCalls as Calls;
+    let call = match Calls::decode(input).ok()? {
+        Calls::InitiateEscrow(_) => EscrowCall::Initiate(EscrowAsset::Native { amount: value }),
+        Calls::InitiateERC20Escrow(call) => EscrowCall::Initiate(EscrowAsset::Erc20 { amount: call.amount }),
+        Calls::InitiateERC721Escrow(_) => EscrowCall::Initiate(EscrowAsset::Erc721 { token_id: None }),
+        Calls::ReleaseEscrow(_) => EscrowCall::Release(EscrowKind::Native),
+        Calls::ReleaseERC20Escrow(_) => EscrowCall::Release(EscrowKind::Erc20),
+        Calls::ReleaseERC721Escrow(_) => EscrowCall::Release(EscrowKind::Erc721),
+        Calls::RefundEscrow(_) => EscrowCall::Refund(EscrowKind::Native),
+        Calls::RefundERC20Escrow(_) => EscrowCall::Refund(EscrowKind::Erc20),
+        Calls::RefundERC721Escrow(_) => EscrowCall::Refund(EscrowKind::Erc721),
+        _ => return None,
+    };
+    Some(call)
+}
+
+/// Escrows reconstructed from successful escrow transactions.
+///
+/// The contract keeps at most one open escrow per depositor and asset kind,
+/// and release and refund take no arguments, so calls are matched to the
+/// depositor's open escrow of the same kind.
+#[derive(Debug, Clone, Default)]
+pub struct EscrowLedger {
+    escrows: BTreeMap<(Address, EscrowKind), Vec<Escrow>>,
+}
+
+impl EscrowLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply a successful call sent by `depositor` in a block with
+    /// `timestamp`. Calls must be applied in chain order. Releases and
+    /// refunds with no open escrow, e.g. for one opened before indexing
+    /// started, are ignored, as is an initiate while the depositor's escrow
+    /// of that kind is still open: the contract holds one per kind.
+    pub fn apply(&mut self, depositor: Address, call: &EscrowCall, transaction_hash: H256, block_number: u64, timestamp: u64) {
+        let (kind, to) = match *call {
+            EscrowCall::Initiate(asset) => {
+                if self.current(depositor, asset.kind()).is_some_and(Escrow::is_active) {
+                    log::warn!("ignoring {:?} escrow call {:?} for {:?}: an escrow is already open", asset.kind(), transaction_hash, depositor);
+                    return;
+                }
+                let mut escrow = Escrow::new(depositor, asset);
+                escrow.state = EscrowState::Funded;
+                escrow.transitions.push(EscrowTransition {
+                    state: EscrowState::Funded,
+                    transaction_hash: Some(transaction_hash),
+                    block_number: Some(block_number),
+                    timestamp: Some(timestamp),
+                });
+                self.escrows.entry((depositor, asset.kind())).or_default().push(escrow);
+                return;
+            }
+            EscrowCall::Release(kind) => (kind, EscrowState::Released),
+            EscrowCall::Refund(kind) => (kind, EscrowState::Refunded),
+        };
+        let current = self.escrows.get_mut(&(depositor, kind)).and_then(|escrows| escrows.last_mut());
+        match current {
+            Some(escrow) if escrow.is_active() => {
+                match escrow.transition(to, Some(transaction_hash), Some(block_number)) {
+                    Ok(()) => {
+                        if let Some(last) = escrow.transitions.last_mut() {
+                            last.timestamp = Some(timestamp);
+                        }
+                    }
+                    Err(e) => log::warn!("ignoring {:?} escrow call {:?} for {:?}: {}", kind, transaction_hash, depositor, e),
+                }
+            }
+            _ => log::debug!("ignoring {} of untracked {:?} escrow for {:?}", to, kind, depositor),
+        }
+    }
+
+    /// The depositor's latest escrow of `kind`.
+    pub fn current(&self, depositor: Address, kind: EscrowKind) -> Option<&Escrow> {
+        self.history(depositor, kind).last()
+    }
+
+    pub fn status(&self, depositor: Address, kind: EscrowKind) -> Option<EscrowState> {
+        self.current(depositor, kind).map(Escrow::state)
+    }
+
+    /// Every escrow of `kind` the depositor opened, oldest first.
+    pub fn history(&self, depositor: Address, kind: EscrowKind) -> &[Escrow] {
+        self.escrows.get(&(depositor, kind)).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Escrows not yet released or refunded.
+    pub fn active(&self) -> impl Iterator<Item = &Escrow> {
+        self.escrows.values().filter_map(|escrows| escrows.last()).filter(|escrow| escrow.is_active())
+    }
+}
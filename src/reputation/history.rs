@@ -0,0 +1,417 @@
+// src/reputation/history.rs
+
+use ethers::abi::AbiDecode;
+use ethers::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use super::escrow::{decode_escrow_call, EscrowCall, EscrowLedger};
+use super::manager::ReputationManagerContractCalls;
+use super::models::Role;
+
+/// What a score update transaction changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScoreChange {
+    /// An `updateScore` call.
+    Action { role: Role, action_type: [u8; 32], success: bool },
+    /// An `updateProductScore` call.
+    ProductScore { product_hash: [u8; 32], score: U256 },
+    /// A `setActionWeight` call, affecting later updates for the DID.
+    ActionWeight { action_type: [u8; 32], weight: U256 },
+}
+
+/// A successful score update and where it was mined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreEvent {
+    pub did: Address,
+    pub block_number: u64,
+    pub transaction_index: u64,
+    pub transaction_hash: H256,
+    /// The block timestamp.
+    pub timestamp: u64,
+    pub change: ScoreChange,
+}
+
+impl ScoreEvent {
+    fn action(&self, role: Option<Role>) -> Option<([u8; 32], bool)> {
+        match self.change {
+            ScoreChange::Action { role: r, action_type, success } if role.is_none_or(|role| role == r) => {
+                Some((action_type, success))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Success and failure counts for a set of actions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ActionStats {
+    pub successes: u64,
+    pub failures: u64,
+}
+
+impl ActionStats {
+    pub fn total(&self) -> u64 {
+        self.successes + self.failures
+    }
+
+    /// `None` when nothing was recorded.
+    pub fn success_rate(&self) -> Option<f64> {
+        (self.total() > 0).then(|| self.successes as f64 / self.total() as f64)
+    }
+
+    fn record(&mut self, success: bool) {
+        if success {
+            self.successes += 1;
+        } else {
+            self.failures += 1;
+        }
+    }
+}
+
+/// Action counts for one time bucket, `[start, start + width)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeBucket {
+    pub start: u64,
+    pub stats: ActionStats,
+}
+
+/// Exponential decay applied by `ReputationHistory::decayed_score`. Each
+/// action contributes `+weight` on success and `-weight` on failure, halved
+/// for every `half_life` of age.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecayModel {
+    pub half_life: Duration,
+    /// Weight of actions without an explicit weight.
+    pub default_weight: f64,
+    weights: HashMap<[u8; 32], f64>,
+}
+
+impl DecayModel {
+    pub fn new(half_life: Duration) -> Self {
+        Self { half_life, default_weight: 1.0, weights: HashMap::new() }
+    }
+
+    pub fn with_default_weight(mut self, weight: f64) -> Self {
+        self.default_weight = weight;
+        self
+    }
+
+    pub fn with_weight(mut self, action_type: impl Into<[u8; 32]>, weight: f64) -> Self {
+        self.weights.insert(action_type.into(), weight);
+        self
+    }
+
+    pub fn weight(&self, action_type: &[u8; 32]) -> f64 {
+        self.weights.get(action_type).copied().unwrap_or(self.default_weight)
+    }
+
+    /// The fraction of its weight an action of age `age` seconds keeps.
+    pub fn factor(&self, age: u64) -> f64 {
+        let half_life = self.half_life.as_secs_f64();
+        if half_life == 0.0 {
+            return if age == 0 { 1.0 } else { 0.0 };
+        }
+        0.5f64.powf(age as f64 / half_life)
+    }
+}
+
+/// Every indexed score update, grouped by DID in chain order.
+#[derive(Debug, Clone, Default)]
+pub struct ReputationHistory {
+    events: BTreeMap<Address, Vec<ScoreEvent>>,
+    last_block: Option<u64>,
+}
+
+impl ReputationHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an event. Events must be recorded in chain order.
+    pub fn record(&mut self, event: ScoreEvent) {
+        self.last_block = Some(self.last_block.map_or(event.block_number, |b| b.max(event.block_number)));
+        self.events.entry(event.did).or_default().push(event);
+    }
+
+    /// DIDs with at least one recorded update.
+    pub fn dids(&self) -> impl Iterator<Item = &Address> {
+        self.events.keys()
+    }
+
+    pub fn events(&self, did: Address) -> &[ScoreEvent] {
+        self.events.get(&did).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Events with `from <= timestamp < to`.
+    pub fn events_between(&self, did: Address, from: u64, to: u64) -> Vec<&ScoreEvent> {
+        self.events(did).iter().filter(|e| e.timestamp >= from && e.timestamp < to).collect()
+    }
+
+    /// The highest block an event was recorded from.
+    pub fn last_block(&self) -> Option<u64> {
+        self.last_block
+    }
+
+    /// Action counts in consecutive buckets of `bucket` width, from the
+    /// bucket holding the first action to the one holding the last. Buckets
+    /// are aligned to multiples of the width; empty ones are included.
+    pub fn time_series(&self, did: Address, role: Option<Role>, bucket: Duration) -> Vec<TimeBucket> {
+        let width = bucket.as_secs().max(1);
+        let mut counts: BTreeMap<u64, ActionStats> = BTreeMap::new();
+        for event in self.events(did) {
+            if let Some((_, success)) = event.action(role) {
+                counts.entry(event.timestamp - event.timestamp % width).or_default().record(success);
+            }
+        }
+        let (Some(&first), Some(&last)) = (counts.keys().next(), counts.keys().next_back()) else {
+            return Vec::new();
+        };
+        (first..=last)
+            .step_by(width as usize)
+            .map(|start| TimeBucket { start, stats: counts.get(&start).copied().unwrap_or_default() })
+            .collect()
+    }
+
+    /// Counts per action type.
+    pub fn breakdown(&self, did: Address, role: Option<Role>) -> BTreeMap<[u8; 32], ActionStats> {
+        let mut breakdown: BTreeMap<[u8; 32], ActionStats> = BTreeMap::new();
+        for (action_type, success) in self.events(did).iter().filter_map(|e| e.action(role)) {
+            breakdown.entry(action_type).or_default().record(success);
+        }
+        breakdown
+    }
+
+    /// Counts across all action types.
+    pub fn stats(&self, did: Address, role: Option<Role>) -> ActionStats {
+        let mut stats = ActionStats::default();
+        for (_, success) in self.events(did).iter().filter_map(|e| e.action(role)) {
+            stats.record(success);
+        }
+        stats
+    }
+
+    pub fn success_rate(&self, did: Address, role: Option<Role>) -> Option<f64> {
+        self.stats(did, role).success_rate()
+    }
+
+    /// A recency-weighted score as of `now`. Actions after `now` are ignored.
+    pub fn decayed_score(&self, did: Address, role: Option<Role>, model: &DecayModel, now: u64) -> f64 {
+        self.events(did)
+            .iter()
+            .filter(|e| e.timestamp <= now)
+            .filter_map(|e| e.action(role).map(|action| (e.timestamp, action)))
+            .map(|(timestamp, (action_type, success))| {
+                let contribution = model.weight(&action_type) * model.factor(now - timestamp);
+                if success {
+                    contribution
+                } else {
+                    -contribution
+                }
+            })
+            .sum()
+    }
+
+    /// Every score set for a product, as `(timestamp, score)`.
+    pub fn product_score_history(&self, did: Address, product_hash: [u8; 32]) -> Vec<(u64, U256)> {
+        self.events(did)
+            .iter()
+            .filter_map(|e| match e.change {
+                ScoreChange::ProductScore { product_hash: p, score } if p == product_hash => Some((e.timestamp, score)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Decode a `ReputationManager` call into the DID and change it makes, if
+/// it's a score update or weight change.
+pub fn decode_score_change(input: &[u8]) -> Option<(Address, ScoreChange)> {
+    match ReputationManagerContractCalls::decode(input).ok()? {
+        ReputationManagerContractCalls::UpdateScore(call) => Some((
+            call.did,
+            ScoreChange::Action { role: Role::from_is_producer(call.is_producer), action_type: call.action_type, success: call.success },
+        )),
+        ReputationManagerContractCalls::UpdateProductScore(call) => Some((
+            call.did,
+            ScoreChange::ProductScore { product_hash: call.product_hash, score: call.new_score },
+        )),
+        ReputationManagerContractCalls::SetActionWeight(call) => Some((
+            call.did,
+            ScoreChange::ActionWeight { action_type: call.action_type, weight: call.weight },
+        )),
+        _ => None,
+    }
+}
+
+/// Reconstructs score history by scanning blocks for successful
+/// `updateScore`, `updateProductScore` and `setActionWeight` transactions to
+/// the contract. Escrow calls found along the way feed an `EscrowLedger`.
+///
+/// The contract emits no score events, so every block in range is fetched
+/// with its transactions. Start from the deployment block, and prefer a
+/// node close to the caller.
+pub struct ReputationIndexer<M: Middleware> {
+    client: Arc<M>,
+    contract: Address,
+    history: ReputationHistory,
+    escrows: EscrowLedger,
+    next_block: u64,
+    /// The latest block seen by the last sync.
+    head: Option<u64>,
+}
+
+/// What one block holds for the indexer, read but not yet recorded.
+#[derive(Default)]
+struct ScannedBlock {
+    timestamp: u64,
+    events: Vec<ScoreEvent>,
+    escrow_calls: Vec<(Address, EscrowCall, H256)>,
+}
+
+impl<M: Middleware + 'static> ReputationIndexer<M> {
+    pub fn new(client: Arc<M>, contract: Address, from_block: u64) -> Self {
+        Self {
+            client,
+            contract,
+            history: ReputationHistory::new(),
+            escrows: EscrowLedger::new(),
+            next_block: from_block,
+            head: None,
+        }
+    }
+
+    pub fn history(&self) -> &ReputationHistory {
+        &self.history
+    }
+
+    /// Escrow status as of the last synced block.
+    pub fn escrows(&self) -> &EscrowLedger {
+        &self.escrows
+    }
+
+    /// Scan up to the latest block, record new updates and return them.
+    pub async fn sync(&mut self) -> Result<Vec<ScoreEvent>, ContractError<M>> {
+        self.sync_blocks(u64::MAX).await
+    }
+
+    /// Scan at most `max_blocks` blocks towards the latest one, record new
+    /// updates and return them. Each block is applied whole once it has been
+    /// read, so a failure never leaves one half-applied. If a block can't be
+    /// read after others were indexed, the error is logged, the indexed
+    /// updates are returned and the block is retried on the next call.
+    pub async fn sync_blocks(&mut self, max_blocks: u64) -> Result<Vec<ScoreEvent>, ContractError<M>> {
+        let latest = self.client.get_block_number().await.map_err(ContractError::from_middleware_error)?.as_u64();
+        self.head = Some(latest);
+        let mut new_events = Vec::new();
+        let mut scanned = 0;
+        while self.next_block <= latest && scanned < max_blocks {
+            let block = match self.scan_block(self.next_block).await {
+                Ok(block) => block,
+                Err(e) if scanned > 0 => {
+                    log::warn!("reputation sync stopped at block {}: {}", self.next_block, e);
+                    break;
+                }
+                Err(e) => return Err(e),
+            };
+            for (depositor, call, transaction_hash) in block.escrow_calls {
+                self.escrows.apply(depositor, &call, transaction_hash, self.next_block, block.timestamp);
+            }
+            for event in block.events {
+                self.history.record(event.clone());
+                new_events.push(event);
+            }
+            self.next_block += 1;
+            scanned += 1;
+        }
+        Ok(new_events)
+    }
+
+    /// Whether the last sync reached the latest block it saw.
+    pub fn is_synced(&self) -> bool {
+        self.head.is_some_and(|head| self.next_block > head)
+    }
+
+    /// The next block to scan.
+    pub fn next_block(&self) -> u64 {
+        self.next_block
+    }
+
+    /// Read the block's successful score updates and escrow calls without
+    /// recording them. A block or receipt the node doesn't have yet is an
+    /// error, so the block is retried rather than skipped.
+    async fn scan_block(&self, number: u64) -> Result<ScannedBlock, ContractError<M>> {
+        let Some(block) = self.client.get_block_with_txs(number).await.map_err(ContractError::from_middleware_error)? else {
+            return Err(not_available(format!("block {}", number)));
+        };
+        let mut transactions: Vec<&Transaction> = block.transactions.iter().filter(|tx| tx.to == Some(self.contract)).collect();
+        transactions.sort_by_key(|tx| tx.transaction_index);
+
+        let mut scanned = ScannedBlock { timestamp: block.timestamp.as_u64(), ..Default::default() };
+        for tx in transactions {
+            let score_change = decode_score_change(&tx.input);
+            let escrow_call = decode_escrow_call(&tx.input, tx.value);
+            if score_change.is_none() && escrow_call.is_none() {
+                continue;
+            }
+            let receipt = self
+                .client
+                .get_transaction_receipt(tx.hash)
+                .await
+                .map_err(ContractError::from_middleware_error)?
+                .ok_or_else(|| not_available(format!("receipt for {:?}", tx.hash)))?;
+            if receipt.status.is_none_or(|status| status.is_zero()) {
+                continue;
+            }
+            if let Some(call) = escrow_call {
+                scanned.escrow_calls.push((tx.from, call, tx.hash));
+            }
+            if let Some((did, change)) = score_change {
+                scanned.events.push(ScoreEvent {
+                    did,
+                    block_number: number,
+                    transaction_index: tx.transaction_index.map_or(0, |i| i.as_u64()),
+                    transaction_hash: tx.hash,
+                    timestamp: scanned.timestamp,
+                    change,
+                });
+            }
+        }
+        Ok(scanned)
+    }
+
+    /// Poll for new updates every `interval` and stream them to the returned
+    /// receiver. Failed polls are logged and retried on the next tick; the
+    /// task stops when the receiver is dropped.
+    pub fn watch(mut self, interval: Duration) -> mpsc::Receiver<ScoreEvent> {
+        let (sender, receiver) = mpsc::channel(256);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if sender.is_closed() {
+                    return;
+                }
+                let events = match self.sync().await {
+                    Ok(events) => events,
+                    Err(e) => {
+                        log::warn!("reputation indexer sync failed: {}", e);
+                        continue;
+                    }
+                };
+                for event in events {
+                    if sender.send(event).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        receiver
+    }
+}
+
+fn not_available<M: Middleware>(what: String) -> ContractError<M> {
+    ContractError::ProviderError { e: ProviderError::CustomError(format!("{} not available from the node", what)) }
+}
@@ -1,11 +1,17 @@
 // src/reputation/manager.rs
-use super::models::Reputation;
+use ethers::abi::Tokenizable;
 use ethers::prelude::*;
-use ethers::providers::{Provider, Http};
-use ethers::core::k256::ecdsa::SigningKey;
-use ethers::signers::{LocalWallet, Signer};
+use ethers::signers::LocalWallet;
 use std::sync::Arc;
 
+use super::actions::{ActionRegistry, ActionType, ActionWeights};
+use super::error::ReputationError;
+use super::escrow::{Escrow, EscrowAsset, EscrowKind, EscrowState};
+use super::models::Reputation;
+
+/// How many `getCompleteProfile` calls go into one multicall.
+pub const PROFILE_BATCH_SIZE: usize = 100;
+
 abigen!(
     ReputationManagerContract,
     r#"[
@@ -33,40 +39,128 @@ abigen!(
 pub struct ReputationManager<M: Middleware> {
     pub contract: ReputationManagerContract<M>,
     pub wallet: LocalWallet,
+    multicall_address: Option<Address>,
 }
 
 impl<M: Middleware + 'static> ReputationManager<M> {
     
     pub fn new(address: Address, client: Arc<M>, wallet: LocalWallet) -> Self {
         let contract = ReputationManagerContract::new(address, Arc::clone(&client));
-        Self { contract, wallet }
+        Self { contract, wallet, multicall_address: None }
+    }
+
+    /// Use the Multicall3 contract at `address` for batched queries instead
+    /// of the canonical deployment. Needed on local and private chains.
+    pub fn with_multicall_address(mut self, address: Address) -> Self {
+        self.multicall_address = Some(address);
+        self
     }
     
-    pub async fn update_score(&self, did: Address, is_producer: bool, action_type: [u8; 32], success: bool) -> Result<TransactionReceipt, ContractError<M>> {
+    /// `action_type` is an `ActionType`, a `KnownAction` or a raw id.
+    pub async fn update_score(&self, did: Address, is_producer: bool, action_type: impl Into<[u8; 32]>, success: bool) -> Result<TransactionReceipt, ContractError<M>> {
         let tx = self.contract.update_score(did, is_producer, action_type.into(), success);
         let pending_tx = tx.send().await?;
         Ok(pending_tx.await?.expect("Transaction failed"))
     }
 
-    pub async fn set_action_weight(&self, did: Address, action_type: [u8; 32], weight: U256) -> Result<TransactionReceipt, ContractError<M>> {
+    pub async fn set_action_weight(&self, did: Address, action_type: impl Into<[u8; 32]>, weight: U256) -> Result<TransactionReceipt, ContractError<M>> {
         let tx = self.contract.set_action_weight(did, action_type.into(), weight);
         let pending_tx = tx.send().await?;
         Ok(pending_tx.await?.expect("Transaction failed"))
     }
 
+    /// Set every configured weight for `did`, one transaction per action in
+    /// name order. All names are checked against `registry` before anything
+    /// is sent.
+    pub async fn apply_action_weights(
+        &self,
+        did: Address,
+        weights: &ActionWeights,
+        registry: &ActionRegistry,
+    ) -> Result<Vec<(ActionType, TransactionReceipt)>, ReputationError<M>> {
+        let resolved = weights.resolve(registry).map_err(ReputationError::UnknownActions)?;
+        let mut receipts = Vec::with_capacity(resolved.len());
+        for (action, weight) in resolved {
+            let receipt = self.set_action_weight(did, &action, weight).await?;
+            receipts.push((action, receipt));
+        }
+        Ok(receipts)
+    }
+
     pub async fn update_product_score(&self, did: Address, product_hash: [u8; 32], new_score: U256) -> Result<TransactionReceipt, ContractError<M>> {
-        let tx = self.contract.update_product_score(did, product_hash.into(), new_score);
+        let tx = self.contract.update_product_score(did, product_hash, new_score);
         let pending_tx = tx.send().await?;
         Ok(pending_tx.await?.expect("Transaction failed"))
     }
 
+    /// The raw `(consumer_score, producer_score, escrow_balance)` profile.
+    /// Prefer `get_reputation`, which labels the fields.
     pub async fn get_complete_profile(&self, did: Address) -> Result<(U256, U256, U256), ContractError<M>> {
-        let (consumer_score, participant_score, escrow_balance) = self.contract.get_complete_profile(did).call().await?;
-        Ok((consumer_score, participant_score, escrow_balance))
+        let (consumer_score, producer_score, escrow_balance) = self.contract.get_complete_profile(did).call().await?;
+        Ok((consumer_score, producer_score, escrow_balance))
+    }
+
+    pub async fn get_reputation(&self, did: Address) -> Result<Reputation, ContractError<M>> {
+        let (consumer_score, producer_score, escrow_balance) = self.get_complete_profile(did).await?;
+        Ok(Reputation::new(did, consumer_score, producer_score, escrow_balance))
+    }
+
+    /// The reputation as of `block`. Needs an archive node for old blocks.
+    pub async fn get_reputation_at(&self, did: Address, block: u64) -> Result<Reputation, ContractError<M>> {
+        let (consumer_score, producer_score, escrow_balance) =
+            self.contract.get_complete_profile(did).block(block).call().await?;
+        Ok(Reputation::new(did, consumer_score, producer_score, escrow_balance))
+    }
+
+    /// Fetch many DIDs' reputations through Multicall3, in batches of
+    /// `PROFILE_BATCH_SIZE`. Results are in the order of `dids`; a DID whose
+    /// query reverts gets its own error without failing the batch.
+    ///
+    /// Falls back to one call per DID when no multicall address was set and
+    /// the chain has no canonical Multicall3 deployment.
+    pub async fn get_reputations(
+        &self,
+        dids: &[Address],
+    ) -> Result<Vec<(Address, Result<Reputation, ContractError<M>>)>, ContractError<M>> {
+        if dids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut multicall = match Multicall::new(self.contract.client(), self.multicall_address).await {
+            Ok(multicall) => multicall,
+            Err(MulticallError::InvalidChainId(_)) => {
+                let mut reputations = Vec::with_capacity(dids.len());
+                for &did in dids {
+                    reputations.push((did, self.get_reputation(did).await));
+                }
+                return Ok(reputations);
+            }
+            Err(e) => return Err(multicall_error(e)),
+        };
+
+        let mut reputations = Vec::with_capacity(dids.len());
+        for batch in dids.chunks(PROFILE_BATCH_SIZE) {
+            multicall.clear_calls();
+            for &did in batch {
+                multicall.add_call(self.contract.get_complete_profile(did), true);
+            }
+            let results = multicall.call_raw().await.map_err(multicall_error)?;
+            for (&did, result) in batch.iter().zip(results) {
+                let reputation = match result {
+                    Ok(token) => <(U256, U256, U256)>::from_token(token)
+                        .map(|(consumer_score, producer_score, escrow_balance)| {
+                            Reputation::new(did, consumer_score, producer_score, escrow_balance)
+                        })
+                        .map_err(ContractError::DetokenizationError),
+                    Err(revert) => Err(ContractError::Revert(revert)),
+                };
+                reputations.push((did, reputation));
+            }
+        }
+        Ok(reputations)
     }
 
     pub async fn get_product_score(&self, did: Address, product_hash: [u8; 32]) -> Result<U256, ContractError<M>> {
-        let product_score = self.contract.get_product_score(did, product_hash.into()).call().await?;
+        let product_score = self.contract.get_product_score(did, product_hash).call().await?;
         Ok(product_score)
     }
 
@@ -124,6 +218,48 @@ impl<M: Middleware + 'static> ReputationManager<M> {
         Ok(pending_tx.await?.expect("Transaction failed"))
     }
 
+    /// Escrow `asset` from the wallet and return the escrow, `Funded` once
+    /// the initiating transaction is mined. `initiateERC721Escrow` takes no
+    /// token id, so an ERC-721 token id is only recorded on the returned
+    /// escrow.
+    pub async fn open_escrow(&self, asset: EscrowAsset) -> Result<Escrow, ReputationError<M>> {
+        let mut escrow = Escrow::new(self.wallet.address(), asset);
+        let tx = match asset {
+            EscrowAsset::Native { amount } => self.contract.initiate_escrow().value(amount),
+            EscrowAsset::Erc20 { amount } => self.contract.initiate_erc20_escrow(amount),
+            EscrowAsset::Erc721 { .. } => self.contract.initiate_erc721_escrow(),
+        };
+        let receipt = mined(tx.send().await?).await?;
+        escrow.transition(EscrowState::Funded, Some(receipt.transaction_hash), receipt.block_number.map(|b| b.as_u64()))?;
+        Ok(escrow)
+    }
+
+    /// Release a funded or disputed escrow to the counterparty.
+    pub async fn release(&self, escrow: &mut Escrow) -> Result<TransactionReceipt, ReputationError<M>> {
+        escrow.check(EscrowState::Released)?;
+        let tx = match escrow.kind() {
+            EscrowKind::Native => self.contract.release_escrow(),
+            EscrowKind::Erc20 => self.contract.release_erc20_escrow(),
+            EscrowKind::Erc721 => self.contract.release_erc721_escrow(),
+        };
+        let receipt = mined(tx.send().await?).await?;
+        escrow.transition(EscrowState::Released, Some(receipt.transaction_hash), receipt.block_number.map(|b| b.as_u64()))?;
+        Ok(receipt)
+    }
+
+    /// Return a funded or disputed escrow to the depositor.
+    pub async fn refund(&self, escrow: &mut Escrow) -> Result<TransactionReceipt, ReputationError<M>> {
+        escrow.check(EscrowState::Refunded)?;
+        let tx = match escrow.kind() {
+            EscrowKind::Native => self.contract.refund_escrow(),
+            EscrowKind::Erc20 => self.contract.refund_erc20_escrow(),
+            EscrowKind::Erc721 => self.contract.refund_erc721_escrow(),
+        };
+        let receipt = mined(tx.send().await?).await?;
+        escrow.transition(EscrowState::Refunded, Some(receipt.transaction_hash), receipt.block_number.map(|b| b.as_u64()))?;
+        Ok(receipt)
+    }
+
     pub async fn set_identity_manager(&self, new_identity_manager: Address) -> Result<TransactionReceipt, ContractError<M>> {
         let tx = self.contract.set_identity_manager(new_identity_manager);
         let pending_tx = tx.send().await?;
@@ -147,4 +283,22 @@ impl<M: Middleware + 'static> ReputationManager<M> {
         let pending_tx = tx.send().await?;
         Ok(pending_tx.await?.expect("Transaction failed"))
     }
-}
\ No newline at end of file
+}
+
+/// Every call in a batch allows failure, so the only non-contract multicall
+/// errors are chain lookups, which `get_reputations` handles itself.
+fn multicall_error<M: Middleware>(e: MulticallError<M>) -> ContractError<M> {
+    match e {
+        MulticallError::ContractError(e) => e,
+        e => ContractError::ProviderError { e: ProviderError::CustomError(e.to_string()) },
+    }
+}
+
+/// Wait for a sent transaction's receipt, failing with `Dropped` if the
+/// transaction left the mempool without being mined.
+pub(crate) async fn mined<M: Middleware>(
+    pending_tx: PendingTransaction<'_, M::Provider>,
+) -> Result<TransactionReceipt, ReputationError<M>> {
+    let tx_hash = *pending_tx;
+    pending_tx.await.map_err(ContractError::from)?.ok_or(ReputationError::Dropped(tx_hash))
+}
@@ -1,5 +1,26 @@
+mod actions;
+mod error;
+mod escrow;
+mod history;
 mod manager;
 mod models;
+mod simulator;
+mod tokens;
+mod watcher;
 
-pub use manager::ReputationManager;
-pub use models::Reputation;
\ No newline at end of file
+pub use actions::{ActionRegistry, ActionType, ActionWeights, KnownAction};
+pub use error::ReputationError;
+pub use escrow::{
+    decode_escrow_call, Escrow, EscrowAsset, EscrowCall, EscrowError, EscrowKind, EscrowLedger, EscrowState, EscrowTransition,
+};
+pub use history::{
+    decode_score_change, ActionStats, DecayModel, ReputationHistory, ReputationIndexer, ScoreChange, ScoreEvent, TimeBucket,
+};
+pub use manager::{ReputationManager, PROFILE_BATCH_SIZE};
+pub use models::{FixedPoint, Reputation, Role, ETHER_DECIMALS, MAX_DECIMALS, SCORE_DECIMALS};
+pub use simulator::{ScoreMismatch, ScoreSimulator, ScoreUpdate, ScoringRules, BPS};
+pub use tokens::{
+    permit_digest, permit_typehash, sign_permit, Approval, ApprovalStrategy, Erc20Token, Erc721Token, NftApprovalScope,
+    PermitSignature, DEFAULT_PERMIT_VALIDITY,
+};
+pub use watcher::{EscrowNotification, EscrowWatcher, DEFAULT_ESCROW_TIMEOUT, DEFAULT_SYNC_BLOCK_BUDGET};
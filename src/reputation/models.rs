@@ -1,22 +1,132 @@
 // src/reputation/models.rs
 
 use ethers::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::fmt;
 
-#[derive(Debug, Clone)]
+/// Decimals assumed for on-chain scores, so a raw value of `1e18` reads as
+/// 1.0. The contract exposes no decimals view and its source doesn't fix a
+/// scale; 18 follows the usual WAD convention. Deployments that store scores
+/// differently should build `FixedPoint::new(raw, decimals)` themselves.
+pub const SCORE_DECIMALS: u32 = 18;
+/// Decimals of the native currency held in escrow (wei per ether).
+pub const ETHER_DECIMALS: u32 = 18;
+/// The most decimals a `FixedPoint` can use: `10^77` is the largest power of
+/// ten that fits in a `U256`.
+pub const MAX_DECIMALS: u32 = 77;
+
+/// A fixed-point number as stored on-chain: `raw / 10^decimals`.
+///
+/// `decimals` above `MAX_DECIMALS` are treated as `MAX_DECIMALS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FixedPoint {
+    pub raw: U256,
+    pub decimals: u32,
+}
+
+impl FixedPoint {
+    /// `decimals` is clamped to `MAX_DECIMALS`.
+    pub fn new(raw: U256, decimals: u32) -> Self {
+        Self { raw, decimals: decimals.min(MAX_DECIMALS) }
+    }
+
+    /// A score in the assumed `SCORE_DECIMALS` representation.
+    pub fn score(raw: U256) -> Self {
+        Self::new(raw, SCORE_DECIMALS)
+    }
+
+    /// The integer part, rounded down.
+    pub fn whole(&self) -> U256 {
+        self.raw / U256::exp10(self.scale_digits())
+    }
+
+    fn scale_digits(&self) -> usize {
+        self.decimals.min(MAX_DECIMALS) as usize
+    }
+
+    /// Lossy conversion for ranking and display. Values beyond `f64` range
+    /// saturate to `f64::MAX`.
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::MAX)
+    }
+}
+
+/// Exact decimal form with trailing fractional zeros removed, e.g. `12.5`.
+impl fmt::Display for FixedPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scale = U256::exp10(self.scale_digits());
+        let whole = self.raw / scale;
+        let fraction = self.raw % scale;
+        if fraction.is_zero() {
+            return write!(f, "{}", whole);
+        }
+        let digits = format!("{:0>width$}", fraction.to_string(), width = self.scale_digits());
+        write!(f, "{}.{}", whole, digits.trim_end_matches('0'))
+    }
+}
+
+/// A DID's on-chain reputation profile, as returned by `getCompleteProfile`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Reputation {
     pub did: Address,
+    /// Raw score earned by acting as a consumer of services.
     pub consumer_score: U256,
+    /// Raw score earned by acting as a producer (service provider).
     pub producer_score: U256,
-    pub eth_balance: U256,
+    /// Wei the DID currently holds in ETH escrow. This field used to be
+    /// `eth_balance`; code naming the old field must switch to this one or
+    /// to the deprecated `eth_balance()` accessor.
+    pub escrow_balance: U256,
 }
 
 impl Reputation {
-    pub fn new(did: Address, consumer_score: U256, producer_score: U256, eth_balance: U256) -> Self {
+    pub fn new(did: Address, consumer_score: U256, producer_score: U256, escrow_balance: U256) -> Self {
         Self {
             did,
             consumer_score,
             producer_score,
-            eth_balance,
+            escrow_balance,
         }
     }
-}
\ No newline at end of file
+
+    pub fn consumer(&self) -> FixedPoint {
+        FixedPoint::score(self.consumer_score)
+    }
+
+    pub fn producer(&self) -> FixedPoint {
+        FixedPoint::score(self.producer_score)
+    }
+
+    #[deprecated(note = "renamed to the `escrow_balance` field")]
+    pub fn eth_balance(&self) -> U256 {
+        self.escrow_balance
+    }
+
+    /// The escrow balance in ether.
+    pub fn escrow(&self) -> FixedPoint {
+        FixedPoint::new(self.escrow_balance, ETHER_DECIMALS)
+    }
+}
+
+/// Which side of an interaction a score update applies to. The contract's
+/// `isProducer` flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Consumer,
+    Producer,
+}
+
+impl Role {
+    pub fn from_is_producer(is_producer: bool) -> Self {
+        if is_producer {
+            Role::Producer
+        } else {
+            Role::Consumer
+        }
+    }
+
+    pub fn is_producer(&self) -> bool {
+        *self == Role::Producer
+    }
+}
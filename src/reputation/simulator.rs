@@ -0,0 +1,228 @@
+// src/reputation/simulator.rs
+
+use ethers::prelude::*;
+use std::collections::HashMap;
+
+use super::history::{ReputationHistory, ScoreChange, ScoreEvent};
+use super::manager::ReputationManager;
+use super::models::{Reputation, Role, SCORE_DECIMALS};
+
+/// Basis points in a whole, for `ScoringRules::failure_penalty_bps`.
+pub const BPS: u32 = 10_000;
+
+/// The scoring rules the simulator applies.
+///
+/// The defaults are assumptions, not read from the contract: its source isn't
+/// part of this SDK and the ABI doesn't expose the rules. They assume that
+///
+/// - `updateScore` adds the DID's weight for the action to its consumer or
+///   producer score on success, and subtracts it on failure, never going
+///   below zero;
+/// - actions without a `setActionWeight` use a weight of one point
+///   (`10^SCORE_DECIMALS`);
+/// - `updateProductScore` overwrites the product score.
+///
+/// Check them against the deployment you target with
+/// `ScoreSimulator::cross_check` before relying on predictions, and change
+/// them where it differs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoringRules {
+    /// Weight of actions the DID has no weight set for.
+    pub default_weight: U256,
+    /// Share of the weight a failure subtracts, in basis points.
+    pub failure_penalty_bps: u32,
+}
+
+impl Default for ScoringRules {
+    fn default() -> Self {
+        Self { default_weight: U256::exp10(SCORE_DECIMALS as usize), failure_penalty_bps: BPS }
+    }
+}
+
+impl ScoringRules {
+    pub fn with_default_weight(mut self, weight: U256) -> Self {
+        self.default_weight = weight;
+        self
+    }
+
+    pub fn with_failure_penalty_bps(mut self, bps: u32) -> Self {
+        self.failure_penalty_bps = bps;
+        self
+    }
+
+    /// The score after one action.
+    pub fn apply(&self, score: U256, weight: U256, success: bool) -> U256 {
+        if success {
+            score.saturating_add(weight)
+        } else {
+            let penalty = weight.saturating_mul(U256::from(self.failure_penalty_bps)) / U256::from(BPS);
+            score.saturating_sub(penalty)
+        }
+    }
+}
+
+/// The effect of one `updateScore`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreUpdate {
+    pub did: Address,
+    pub role: Role,
+    pub action_type: [u8; 32],
+    pub success: bool,
+    pub weight: U256,
+    pub before: U256,
+    pub after: U256,
+}
+
+/// A DID whose simulated scores differ from its on-chain profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScoreMismatch {
+    pub simulated: Reputation,
+    pub on_chain: Reputation,
+}
+
+#[derive(Debug, Clone, Default)]
+struct SimulatedProfile {
+    consumer_score: U256,
+    producer_score: U256,
+    escrow_balance: U256,
+    weights: HashMap<[u8; 32], U256>,
+    product_scores: HashMap<[u8; 32], U256>,
+}
+
+impl SimulatedProfile {
+    fn score(&self, role: Role) -> U256 {
+        match role {
+            Role::Consumer => self.consumer_score,
+            Role::Producer => self.producer_score,
+        }
+    }
+
+    fn score_mut(&mut self, role: Role) -> &mut U256 {
+        match role {
+            Role::Consumer => &mut self.consumer_score,
+            Role::Producer => &mut self.producer_score,
+        }
+    }
+}
+
+/// A pure-Rust model of `ReputationManager` scoring under `ScoringRules`,
+/// for predicting the outcome of `update_score` and replaying indexed
+/// history.
+#[derive(Debug, Clone, Default)]
+pub struct ScoreSimulator {
+    rules: ScoringRules,
+    profiles: HashMap<Address, SimulatedProfile>,
+}
+
+impl ScoreSimulator {
+    pub fn new(rules: ScoringRules) -> Self {
+        Self { rules, profiles: HashMap::new() }
+    }
+
+    pub fn rules(&self) -> &ScoringRules {
+        &self.rules
+    }
+
+    /// Start a DID from its current on-chain profile instead of zero.
+    /// Action weights and product scores are kept.
+    pub fn seed(&mut self, reputation: &Reputation) {
+        let profile = self.profiles.entry(reputation.did).or_default();
+        profile.consumer_score = reputation.consumer_score;
+        profile.producer_score = reputation.producer_score;
+        profile.escrow_balance = reputation.escrow_balance;
+    }
+
+    pub fn set_action_weight(&mut self, did: Address, action_type: impl Into<[u8; 32]>, weight: U256) {
+        self.profiles.entry(did).or_default().weights.insert(action_type.into(), weight);
+    }
+
+    /// The DID's weight for an action, or the default weight if none was set.
+    pub fn action_weight(&self, did: Address, action_type: impl Into<[u8; 32]>) -> U256 {
+        self.profiles
+            .get(&did)
+            .and_then(|profile| profile.weights.get(&action_type.into()).copied())
+            .unwrap_or(self.rules.default_weight)
+    }
+
+    /// Predict an `updateScore` without applying it.
+    pub fn simulate(&self, did: Address, role: Role, action_type: impl Into<[u8; 32]>, success: bool) -> ScoreUpdate {
+        let action_type = action_type.into();
+        let weight = self.action_weight(did, action_type);
+        let before = self.profiles.get(&did).map(|profile| profile.score(role)).unwrap_or_default();
+        ScoreUpdate { did, role, action_type, success, weight, before, after: self.rules.apply(before, weight, success) }
+    }
+
+    /// Apply an `updateScore`.
+    pub fn update_score(&mut self, did: Address, role: Role, action_type: impl Into<[u8; 32]>, success: bool) -> ScoreUpdate {
+        let update = self.simulate(did, role, action_type, success);
+        *self.profiles.entry(did).or_default().score_mut(role) = update.after;
+        update
+    }
+
+    pub fn update_product_score(&mut self, did: Address, product_hash: [u8; 32], score: U256) {
+        self.profiles.entry(did).or_default().product_scores.insert(product_hash, score);
+    }
+
+    pub fn product_score(&self, did: Address, product_hash: [u8; 32]) -> U256 {
+        self.profiles
+            .get(&did)
+            .and_then(|profile| profile.product_scores.get(&product_hash).copied())
+            .unwrap_or_default()
+    }
+
+    /// Apply one indexed change. Returns the update for `updateScore` calls.
+    pub fn apply(&mut self, did: Address, change: &ScoreChange) -> Option<ScoreUpdate> {
+        match *change {
+            ScoreChange::Action { role, action_type, success } => Some(self.update_score(did, role, action_type, success)),
+            ScoreChange::ProductScore { product_hash, score } => {
+                self.update_product_score(did, product_hash, score);
+                None
+            }
+            ScoreChange::ActionWeight { action_type, weight } => {
+                self.set_action_weight(did, action_type, weight);
+                None
+            }
+        }
+    }
+
+    /// Apply events in chain order, returning the score updates.
+    pub fn replay<'a>(&mut self, events: impl IntoIterator<Item = &'a ScoreEvent>) -> Vec<ScoreUpdate> {
+        events.into_iter().filter_map(|event| self.apply(event.did, &event.change)).collect()
+    }
+
+    /// Replay every DID's indexed history.
+    pub fn replay_history(&mut self, history: &ReputationHistory) -> Vec<ScoreUpdate> {
+        let dids: Vec<Address> = history.dids().copied().collect();
+        dids.into_iter().flat_map(|did| self.replay(history.events(did))).collect()
+    }
+
+    /// The simulated profile in `get_reputation`'s shape. The escrow balance
+    /// is whatever was seeded; escrow movements aren't modelled.
+    pub fn reputation(&self, did: Address) -> Reputation {
+        let profile = self.profiles.get(&did).cloned().unwrap_or_default();
+        Reputation::new(did, profile.consumer_score, profile.producer_score, profile.escrow_balance)
+    }
+
+    /// Compare consumer and producer scores with an on-chain profile.
+    pub fn compare(&self, on_chain: &Reputation) -> Option<ScoreMismatch> {
+        let simulated = self.reputation(on_chain.did);
+        let matches = simulated.consumer_score == on_chain.consumer_score && simulated.producer_score == on_chain.producer_score;
+        (!matches).then(|| ScoreMismatch { simulated, on_chain: on_chain.clone() })
+    }
+
+    /// Fetch the DIDs' profiles and report every one that disagrees with
+    /// the simulation.
+    pub async fn cross_check<M: Middleware + 'static>(
+        &self,
+        manager: &ReputationManager<M>,
+        dids: &[Address],
+    ) -> Result<Vec<ScoreMismatch>, ContractError<M>> {
+        let mut mismatches = Vec::new();
+        for (_, reputation) in manager.get_reputations(dids).await? {
+            if let Some(mismatch) = self.compare(&reputation?) {
+                mismatches.push(mismatch);
+            }
+        }
+        Ok(mismatches)
+    }
+}
@@ -0,0 +1,250 @@
+// src/reputation/tokens.rs
+
+use ethers::abi::{encode, Token};
+use ethers::prelude::*;
+use ethers::utils::keccak256;
+use std::time::Duration;
+
+use crate::utils::unix_timestamp;
+
+use super::error::ReputationError;
+use super::escrow::{Escrow, EscrowAsset};
+use super::manager::{mined, ReputationManager};
+
+abigen!(
+    Erc20Token,
+    r#"[
+        function balanceOf(address owner) external view returns (uint256)
+        function allowance(address owner, address spender) external view returns (uint256)
+        function approve(address spender, uint256 value) external returns (bool)
+        function nonces(address owner) external view returns (uint256)
+        function DOMAIN_SEPARATOR() external view returns (bytes32)
+        function permit(address owner, address spender, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s) external
+    ]"#,
+);
+
+abigen!(
+    Erc721Token,
+    r#"[
+        function ownerOf(uint256 tokenId) external view returns (address)
+        function getApproved(uint256 tokenId) external view returns (address)
+        function isApprovedForAll(address owner, address operator) external view returns (bool)
+        function approve(address to, uint256 tokenId) external
+        function setApprovalForAll(address operator, bool approved) external
+    ]"#,
+);
+
+/// How long a permit signature stays valid by default.
+pub const DEFAULT_PERMIT_VALIDITY: Duration = Duration::from_secs(20 * 60);
+
+/// The EIP-2612 `Permit` struct type hash.
+pub fn permit_typehash() -> [u8; 32] {
+    keccak256("Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)")
+}
+
+/// The EIP-712 digest a permit signs, given the token's `DOMAIN_SEPARATOR`.
+pub fn permit_digest(domain_separator: [u8; 32], owner: Address, spender: Address, value: U256, nonce: U256, deadline: U256) -> H256 {
+    let struct_hash = keccak256(encode(&[
+        Token::FixedBytes(permit_typehash().to_vec()),
+        Token::Address(owner),
+        Token::Address(spender),
+        Token::Uint(value),
+        Token::Uint(nonce),
+        Token::Uint(deadline),
+    ]));
+    let mut message = Vec::with_capacity(66);
+    message.extend_from_slice(b"\x19\x01");
+    message.extend_from_slice(&domain_separator);
+    message.extend_from_slice(&struct_hash);
+    H256::from(keccak256(message))
+}
+
+/// A signed EIP-2612 permit, split the way `permit` takes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PermitSignature {
+    pub deadline: U256,
+    pub v: u8,
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+}
+
+/// Sign a permit letting `spender` move `value` of the wallet's tokens.
+pub fn sign_permit(
+    wallet: &LocalWallet,
+    domain_separator: [u8; 32],
+    spender: Address,
+    value: U256,
+    nonce: U256,
+    deadline: U256,
+) -> Result<PermitSignature, WalletError> {
+    let digest = permit_digest(domain_separator, wallet.address(), spender, value, nonce, deadline);
+    let signature = wallet.sign_hash(digest)?;
+    let mut r = [0u8; 32];
+    let mut s = [0u8; 32];
+    signature.r.to_big_endian(&mut r);
+    signature.s.to_big_endian(&mut s);
+    Ok(PermitSignature { deadline, v: signature.v as u8, r, s })
+}
+
+/// How to grant an ERC-20 allowance that isn't already in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalStrategy {
+    /// Always send `approve`.
+    Approve,
+    /// Use an EIP-2612 `permit` valid for the given time if the token
+    /// supports it, and `approve` otherwise.
+    PreferPermit { valid_for: Duration },
+}
+
+impl Default for ApprovalStrategy {
+    fn default() -> Self {
+        ApprovalStrategy::PreferPermit { valid_for: DEFAULT_PERMIT_VALIDITY }
+    }
+}
+
+/// Which ERC-721 approval to grant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NftApprovalScope {
+    /// `approve` for the single token.
+    Token,
+    /// `setApprovalForAll` for the operator.
+    All,
+}
+
+/// What had to be sent for the spender to move the tokens.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Approval {
+    /// The allowance or approval was already in place.
+    Existing,
+    Approved(TransactionReceipt),
+    Permitted(TransactionReceipt),
+    ApprovedForAll(TransactionReceipt),
+}
+
+/// Whether a failed call means the token lacks the function rather than
+/// that the node failed.
+fn is_unsupported<M: Middleware>(e: &ContractError<M>) -> bool {
+    matches!(e, ContractError::Revert(_) | ContractError::AbiError(_) | ContractError::DetokenizationError(_))
+}
+
+impl<M: Middleware + 'static> ReputationManager<M> {
+    /// Make sure `spender`, the ERC-20 escrow contract, may pull `amount` of
+    /// `token` from the wallet, checking the balance first.
+    pub async fn ensure_erc20_allowance(
+        &self,
+        token: Address,
+        spender: Address,
+        amount: U256,
+        strategy: ApprovalStrategy,
+    ) -> Result<Approval, ReputationError<M>> {
+        let owner = self.wallet.address();
+        let erc20 = Erc20Token::new(token, self.contract.client());
+        let available = erc20.balance_of(owner).call().await?;
+        if available < amount {
+            return Err(ReputationError::InsufficientBalance { token, required: amount, available });
+        }
+        if erc20.allowance(owner, spender).call().await? >= amount {
+            return Ok(Approval::Existing);
+        }
+
+        if let ApprovalStrategy::PreferPermit { valid_for } = strategy {
+            if let Some(receipt) = self.permit(&erc20, spender, amount, valid_for).await? {
+                // A non-standard permit can be mined without granting the allowance.
+                if erc20.allowance(owner, spender).call().await? >= amount {
+                    return Ok(Approval::Permitted(receipt));
+                }
+                log::warn!("permit on {:?} did not grant the allowance, sending approve", token);
+            }
+        }
+        let tx = erc20.approve(spender, amount);
+        let receipt = mined(tx.send().await?).await?;
+        Ok(Approval::Approved(receipt))
+    }
+
+    /// Submit a permit, or return `None` if the token doesn't implement
+    /// EIP-2612, including tokens whose `permit` reverts.
+    async fn permit(
+        &self,
+        erc20: &Erc20Token<M>,
+        spender: Address,
+        amount: U256,
+        valid_for: Duration,
+    ) -> Result<Option<TransactionReceipt>, ReputationError<M>> {
+        let owner = self.wallet.address();
+        let domain_separator = match erc20.domain_separator().call().await {
+            Ok(domain_separator) => domain_separator,
+            Err(e) if is_unsupported(&e) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let nonce = match erc20.nonces(owner).call().await {
+            Ok(nonce) => nonce,
+            Err(e) if is_unsupported(&e) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let deadline = U256::from(unix_timestamp() + valid_for.as_secs());
+        let permit = sign_permit(&self.wallet, domain_separator, spender, amount, nonce, deadline)
+            .map_err(|e| ReputationError::Signing(e.to_string()))?;
+
+        let tx = erc20.permit(owner, spender, amount, permit.deadline, permit.v, permit.r, permit.s);
+        let pending_tx = match tx.send().await {
+            Ok(pending_tx) => pending_tx,
+            Err(e) if is_unsupported(&e) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let receipt = mined(pending_tx).await?;
+        Ok(Some(receipt))
+    }
+
+    /// Make sure `spender`, the ERC-721 escrow contract, may pull `token_id`,
+    /// checking the wallet owns it first.
+    pub async fn ensure_erc721_approval(
+        &self,
+        token: Address,
+        spender: Address,
+        token_id: U256,
+        scope: NftApprovalScope,
+    ) -> Result<Approval, ReputationError<M>> {
+        let owner = self.wallet.address();
+        let erc721 = Erc721Token::new(token, self.contract.client());
+        let holder = erc721.owner_of(token_id).call().await?;
+        if holder != owner {
+            return Err(ReputationError::NotTokenOwner { token, token_id, owner: holder });
+        }
+        if erc721.is_approved_for_all(owner, spender).call().await? || erc721.get_approved(token_id).call().await? == spender {
+            return Ok(Approval::Existing);
+        }
+
+        let (tx, for_all) = match scope {
+            NftApprovalScope::Token => (erc721.approve(spender, token_id), false),
+            NftApprovalScope::All => (erc721.set_approval_for_all(spender, true), true),
+        };
+        let receipt = mined(tx.send().await?).await?;
+        Ok(if for_all { Approval::ApprovedForAll(receipt) } else { Approval::Approved(receipt) })
+    }
+
+    /// Grant the allowance if needed, then escrow `amount` of `token`.
+    pub async fn open_erc20_escrow(
+        &self,
+        token: Address,
+        spender: Address,
+        amount: U256,
+        strategy: ApprovalStrategy,
+    ) -> Result<(Approval, Escrow), ReputationError<M>> {
+        let approval = self.ensure_erc20_allowance(token, spender, amount, strategy).await?;
+        let escrow = self.open_escrow(EscrowAsset::Erc20 { amount }).await?;
+        Ok((approval, escrow))
+    }
+
+    /// Grant the approval if needed, then escrow `token_id`.
+    pub async fn open_erc721_escrow(
+        &self,
+        token: Address,
+        spender: Address,
+        token_id: U256,
+        scope: NftApprovalScope,
+    ) -> Result<(Approval, Escrow), ReputationError<M>> {
+        let approval = self.ensure_erc721_approval(token, spender, token_id, scope).await?;
+        let escrow = self.open_escrow(EscrowAsset::Erc721 { token_id: Some(token_id) }).await?;
+        Ok((approval, escrow))
+    }
+}
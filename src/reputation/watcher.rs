@@ -0,0 +1,227 @@
+// src/reputation/watcher.rs
+
+use ethers::prelude::*;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::utils::unix_timestamp;
+
+use super::escrow::{Escrow, EscrowKind, EscrowLedger, EscrowState};
+use super::history::ReputationIndexer;
+use super::manager::ReputationManager;
+
+/// How long an escrow may stay open before the watcher refunds it.
+pub const DEFAULT_ESCROW_TIMEOUT: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// How many blocks the watcher indexes per pass.
+pub const DEFAULT_SYNC_BLOCK_BUDGET: u64 = 1_000;
+
+/// Something that happened to an escrow the watcher tracks.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EscrowNotification {
+    /// A funded escrow was found and will be refunded at `deadline`.
+    Tracked { escrow: Escrow, deadline: u64 },
+    /// The escrow moved from `from` to its current state. `automatic` is set
+    /// for refunds the watcher sent itself.
+    Transition { escrow: Escrow, from: EscrowState, automatic: bool },
+    /// The deadline passed but the refund failed. It's retried on the next pass.
+    RefundFailed { escrow: Escrow, error: String },
+}
+
+#[derive(Debug, Clone)]
+struct WatchedEscrow {
+    escrow: Escrow,
+    deadline: u64,
+}
+
+/// The transaction that funded an escrow, used to tell successive escrows
+/// of the same kind apart.
+fn funding_transaction(escrow: &Escrow) -> Option<H256> {
+    escrow.transitions().iter().find(|t| t.state == EscrowState::Funded).and_then(|t| t.transaction_hash)
+}
+
+/// Tracks the wallet's escrows and refunds any still open once their
+/// deadline passes.
+///
+/// Escrow status comes from a `ReputationIndexer` over the manager's
+/// contract, so releases and refunds sent from anywhere are observed.
+/// Escrows found by the indexer are due `timeout` after the timestamp of the
+/// block that funded them. Start indexing no later than the oldest escrow to
+/// watch, or its release may go unnoticed.
+///
+/// Each pass indexes at most the block budget. Refunds are only sent once the
+/// indexer has reached the chain head, so a watcher started far behind
+/// catches up over several passes before refunding anything.
+pub struct EscrowWatcher<M: Middleware> {
+    manager: Arc<ReputationManager<M>>,
+    indexer: ReputationIndexer<M>,
+    timeout: Duration,
+    block_budget: u64,
+    watched: BTreeMap<EscrowKind, WatchedEscrow>,
+}
+
+impl<M: Middleware + 'static> EscrowWatcher<M> {
+    pub fn new(manager: Arc<ReputationManager<M>>, from_block: u64) -> Self {
+        let indexer = ReputationIndexer::new(manager.contract.client(), manager.contract.address(), from_block);
+        Self {
+            manager,
+            indexer,
+            timeout: DEFAULT_ESCROW_TIMEOUT,
+            block_budget: DEFAULT_SYNC_BLOCK_BUDGET,
+            watched: BTreeMap::new(),
+        }
+    }
+
+    /// Refund escrows `timeout` after they're funded.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Index at most `blocks` blocks per pass. Must be at least one.
+    pub fn with_block_budget(mut self, blocks: u64) -> Self {
+        self.block_budget = blocks.max(1);
+        self
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Escrow status as of the last synced block.
+    pub fn escrows(&self) -> &EscrowLedger {
+        self.indexer.escrows()
+    }
+
+    /// Tracked escrows and their deadlines.
+    pub fn watched(&self) -> impl Iterator<Item = (&Escrow, u64)> {
+        self.watched.values().map(|w| (&w.escrow, w.deadline))
+    }
+
+    /// Watch an escrow opened by the wallet, refunding it `timeout` from now.
+    pub fn track(&mut self, escrow: Escrow) {
+        let deadline = unix_timestamp() + self.timeout.as_secs();
+        self.track_until(escrow, deadline);
+    }
+
+    /// Watch an escrow opened by the wallet, refunding it at `deadline`.
+    /// Escrows that are closed or belong to another depositor are ignored.
+    pub fn track_until(&mut self, escrow: Escrow, deadline: u64) {
+        if !escrow.is_active() || escrow.depositor != self.manager.wallet.address() {
+            log::debug!("not tracking {} {:?} escrow for {:?}", escrow.state(), escrow.kind(), escrow.depositor);
+            return;
+        }
+        self.watched.insert(escrow.kind(), WatchedEscrow { escrow, deadline });
+    }
+
+    /// Tracked escrows whose deadline has passed at `now`.
+    pub fn due(&self, now: u64) -> Vec<&Escrow> {
+        self.watched.values().filter(|w| now >= w.deadline).map(|w| &w.escrow).collect()
+    }
+
+    /// Bring tracked escrows in line with `ledger`: report transitions of
+    /// tracked escrows, stop tracking closed ones and start tracking the
+    /// wallet's newly funded ones. Escrows without an indexed funding time
+    /// are due `timeout` after `now`.
+    pub fn observe(&mut self, ledger: &EscrowLedger, now: u64) -> Vec<EscrowNotification> {
+        let wallet = self.manager.wallet.address();
+        Self::reconcile(&mut self.watched, wallet, self.timeout, now, ledger)
+    }
+
+    fn reconcile(
+        watched: &mut BTreeMap<EscrowKind, WatchedEscrow>,
+        wallet: Address,
+        timeout: Duration,
+        now: u64,
+        ledger: &EscrowLedger,
+    ) -> Vec<EscrowNotification> {
+        let mut notifications = Vec::new();
+        for kind in [EscrowKind::Native, EscrowKind::Erc20, EscrowKind::Erc721] {
+            if let Some(tracked) = watched.get_mut(&kind) {
+                let funding = funding_transaction(&tracked.escrow);
+                let indexed = ledger
+                    .history(wallet, kind)
+                    .iter()
+                    .find(|escrow| funding.is_some() && funding_transaction(escrow) == funding);
+                if let Some(indexed) = indexed.filter(|escrow| escrow.state() != tracked.escrow.state()) {
+                    let from = tracked.escrow.state();
+                    tracked.escrow = indexed.clone();
+                    notifications.push(EscrowNotification::Transition { escrow: indexed.clone(), from, automatic: false });
+                }
+                if tracked.escrow.is_active() {
+                    continue;
+                }
+                watched.remove(&kind);
+            }
+
+            if let Some(current) = ledger.current(wallet, kind).filter(|escrow| escrow.is_active()) {
+                let deadline = current.funded_at().unwrap_or(now) + timeout.as_secs();
+                watched.insert(kind, WatchedEscrow { escrow: current.clone(), deadline });
+                notifications.push(EscrowNotification::Tracked { escrow: current.clone(), deadline });
+            }
+        }
+        notifications
+    }
+
+    /// Index up to the block budget, report what changed and refund every
+    /// escrow due at `now`. Nothing is refunded if the sync fails or hasn't
+    /// reached the chain head, since the escrow may already have been
+    /// released.
+    pub async fn run_once(&mut self, now: u64) -> Result<Vec<EscrowNotification>, ContractError<M>> {
+        self.indexer.sync_blocks(self.block_budget).await?;
+        let wallet = self.manager.wallet.address();
+        let mut notifications = Self::reconcile(&mut self.watched, wallet, self.timeout, now, self.indexer.escrows());
+        if !self.indexer.is_synced() {
+            log::debug!("escrow watcher catching up at block {}", self.indexer.next_block());
+            return Ok(notifications);
+        }
+
+        let due: Vec<EscrowKind> = self.watched.iter().filter(|(_, w)| now >= w.deadline).map(|(kind, _)| *kind).collect();
+        for kind in due {
+            let Some(tracked) = self.watched.get_mut(&kind) else { continue };
+            let from = tracked.escrow.state();
+            match self.manager.refund(&mut tracked.escrow).await {
+                Ok(_) => {
+                    let escrow = tracked.escrow.clone();
+                    self.watched.remove(&kind);
+                    notifications.push(EscrowNotification::Transition { escrow, from, automatic: true });
+                }
+                Err(e) => {
+                    log::warn!("auto-refund of {:?} escrow failed: {}", kind, e);
+                    notifications.push(EscrowNotification::RefundFailed { escrow: tracked.escrow.clone(), error: e.to_string() });
+                }
+            }
+        }
+        Ok(notifications)
+    }
+
+    /// Run a pass every `interval` and stream notifications to the returned
+    /// receiver. Failed passes are retried on the next tick; the task stops
+    /// when the receiver is dropped.
+    pub fn watch(mut self, interval: Duration) -> mpsc::Receiver<EscrowNotification> {
+        let (sender, receiver) = mpsc::channel(256);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if sender.is_closed() {
+                    return;
+                }
+                let notifications = match self.run_once(unix_timestamp()).await {
+                    Ok(notifications) => notifications,
+                    Err(e) => {
+                        log::warn!("escrow watcher sync failed: {}", e);
+                        continue;
+                    }
+                };
+                for notification in notifications {
+                    if sender.send(notification).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        receiver
+    }
+}
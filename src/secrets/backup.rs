@@ -0,0 +1,249 @@
+// src/secrets/backup.rs
+
+use ecies::{PublicKey, SecretKey};
+use ethers::prelude::*;
+use ethers::types::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::utils::unix_timestamp;
+
+use super::error::SecretsError;
+use super::indexer::SpaceIndex;
+use super::space::SecretsSpace;
+
+/// Current version of the backup manifest and archive formats.
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// Errors raised while sealing or opening a backup archive.
+#[derive(Debug)]
+pub enum BackupError {
+    UnsupportedVersion(u32),
+    /// The archive could not be encrypted or decrypted.
+    Crypto(String),
+    /// The archive signature is invalid or was made by an unexpected key.
+    Signature(String),
+    Format(String),
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::UnsupportedVersion(v) => write!(f, "unsupported backup format version {}", v),
+            BackupError::Crypto(msg) => write!(f, "backup encryption error: {}", msg),
+            BackupError::Signature(msg) => write!(f, "backup signature error: {}", msg),
+            BackupError::Format(msg) => write!(f, "malformed backup: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+/// One secret in a backup together with the delegates authorized for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupEntry {
+    pub identifier: Bytes,
+    pub value: Bytes,
+    pub delegates: Vec<Address>,
+}
+
+/// The plaintext contents of a backup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    pub source_space: Address,
+    pub source_chain_id: U256,
+    pub created_at: u64,
+    pub entries: Vec<BackupEntry>,
+}
+
+/// Where `export_backup` gets the list of identifiers from.
+pub enum ExportSource<'a> {
+    /// An explicit list of identifiers. Delegate grants are not exported.
+    Manifest(Vec<Bytes>),
+    /// Identifiers and grants reconstructed from the space's events.
+    Index(&'a SpaceIndex),
+}
+
+/// A backup encrypted to a recipient key and signed by the exporting wallet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupArchive {
+    pub format_version: u32,
+    pub signer: Address,
+    pub signature: Signature,
+    pub sealed: Bytes,
+}
+
+impl BackupManifest {
+    /// Encrypt the manifest to `recipient` and sign the ciphertext with `wallet`.
+    pub async fn seal(&self, recipient: &PublicKey, wallet: &LocalWallet) -> Result<BackupArchive, BackupError> {
+        let plain = serde_json::to_vec(self).map_err(|e| BackupError::Format(e.to_string()))?;
+        let sealed = ecies::encrypt(&recipient.serialize(), &plain).map_err(|e| BackupError::Crypto(e.to_string()))?;
+        let signature = wallet
+            .sign_message(&sealed)
+            .await
+            .map_err(|e| BackupError::Signature(e.to_string()))?;
+        Ok(BackupArchive {
+            format_version: BACKUP_FORMAT_VERSION,
+            signer: wallet.address(),
+            signature,
+            sealed: Bytes::from(sealed),
+        })
+    }
+}
+
+impl BackupArchive {
+    /// Verify the archive was signed by `expected_signer` and decrypt the
+    /// manifest. The `signer` field is not trusted: anyone who rewrites an
+    /// archive can re-sign it and name themselves as the signer.
+    pub fn open(&self, secret_key: &SecretKey, expected_signer: Address) -> Result<BackupManifest, BackupError> {
+        if self.format_version != BACKUP_FORMAT_VERSION {
+            return Err(BackupError::UnsupportedVersion(self.format_version));
+        }
+        self.signature
+            .verify(self.sealed.as_ref(), expected_signer)
+            .map_err(|e| BackupError::Signature(e.to_string()))?;
+
+        let plain = ecies::decrypt(&secret_key.serialize(), &self.sealed)
+            .map_err(|e| BackupError::Crypto(e.to_string()))?;
+        let manifest: BackupManifest = serde_json::from_slice(&plain).map_err(|e| BackupError::Format(e.to_string()))?;
+        if manifest.format_version != BACKUP_FORMAT_VERSION {
+            return Err(BackupError::UnsupportedVersion(manifest.format_version));
+        }
+        Ok(manifest)
+    }
+
+    pub fn to_json(&self) -> Result<String, BackupError> {
+        serde_json::to_string_pretty(self).map_err(|e| BackupError::Format(e.to_string()))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, BackupError> {
+        serde_json::from_str(json).map_err(|e| BackupError::Format(e.to_string()))
+    }
+}
+
+/// What importing a manifest into a space would change.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackupDiff {
+    /// Identifiers missing from the target space.
+    pub added: Vec<Bytes>,
+    /// Identifiers present in the target with a different value.
+    pub changed: Vec<Bytes>,
+    /// Identifiers present in the target with the same value.
+    pub unchanged: Vec<Bytes>,
+    /// Delegate grants missing from the target space.
+    pub grants: Vec<(Address, Bytes)>,
+    /// Identifiers in the target that are not part of the backup. These are left untouched.
+    pub untracked: Vec<Bytes>,
+}
+
+impl BackupDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.grants.is_empty()
+    }
+}
+
+/// The result of `import_backup`.
+#[derive(Debug, Clone)]
+pub struct ImportReport {
+    pub diff: BackupDiff,
+    pub dry_run: bool,
+    pub receipts: Vec<TransactionReceipt>,
+}
+
+impl<M: Middleware + 'static> SecretsSpace<M> {
+    /// Read every identifier from `source` and collect it into a manifest.
+    pub async fn export_backup(&self, source: ExportSource<'_>) -> Result<BackupManifest, SecretsError<M>> {
+        let identifiers: Vec<(Bytes, Vec<Address>)> = match source {
+            ExportSource::Manifest(identifiers) => identifiers.into_iter().map(|id| (id, Vec::new())).collect(),
+            ExportSource::Index(index) => index
+                .identifiers()
+                .map(|id| (id.clone(), index.delegates(id)))
+                .collect(),
+        };
+
+        let source_chain_id = self
+            .client()
+            .get_chainid()
+            .await
+            .map_err(ContractError::from_middleware_error)?;
+
+        let mut entries = Vec::with_capacity(identifiers.len());
+        for (identifier, delegates) in identifiers {
+            let value = self.get_secret(identifier.clone()).await?;
+            entries.push(BackupEntry { identifier, value, delegates });
+        }
+
+        Ok(BackupManifest {
+            format_version: BACKUP_FORMAT_VERSION,
+            source_space: self.address(),
+            source_chain_id,
+            created_at: unix_timestamp(),
+            entries,
+        })
+    }
+
+    /// Compare a manifest against this space's current contents.
+    pub async fn diff_backup(&self, manifest: &BackupManifest, current: &SpaceIndex) -> Result<BackupDiff, SecretsError<M>> {
+        let mut diff = BackupDiff::default();
+        let mut in_backup = BTreeSet::new();
+
+        for entry in &manifest.entries {
+            in_backup.insert(entry.identifier.clone());
+            if !current.contains(&entry.identifier) {
+                diff.added.push(entry.identifier.clone());
+            } else if self.get_secret(entry.identifier.clone()).await? != entry.value {
+                diff.changed.push(entry.identifier.clone());
+            } else {
+                diff.unchanged.push(entry.identifier.clone());
+            }
+
+            let granted = current.delegates(&entry.identifier);
+            for delegate in &entry.delegates {
+                if !granted.contains(delegate) {
+                    diff.grants.push((*delegate, entry.identifier.clone()));
+                }
+            }
+        }
+
+        diff.untracked = current
+            .identifiers()
+            .filter(|id| !in_backup.contains(*id))
+            .cloned()
+            .collect();
+        Ok(diff)
+    }
+
+    /// Replay a manifest into this space via `add_secret` and `authorize_delegate`.
+    ///
+    /// `current` is the target space's index; pass an empty index for a fresh
+    /// space. With `dry_run` set only the diff is computed.
+    pub async fn import_backup(
+        &self,
+        manifest: &BackupManifest,
+        current: &SpaceIndex,
+        dry_run: bool,
+        max_fee: Option<U256>,
+    ) -> Result<ImportReport, SecretsError<M>> {
+        let diff = self.diff_backup(manifest, current).await?;
+        let mut report = ImportReport { diff, dry_run, receipts: Vec::new() };
+        if dry_run {
+            return Ok(report);
+        }
+
+        for entry in &manifest.entries {
+            if report.diff.added.contains(&entry.identifier) || report.diff.changed.contains(&entry.identifier) {
+                let receipt = self
+                    .add_secret_paid(entry.identifier.clone(), entry.value.clone(), max_fee)
+                    .await?;
+                report.receipts.push(receipt);
+            }
+        }
+        for (delegate, identifier) in &report.diff.grants {
+            let receipt = self.authorize_delegate(*delegate, identifier.clone()).await?;
+            report.receipts.push(receipt);
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,310 @@
+// src/secrets/cache.rs
+
+use ecies::{PublicKey, SecretKey};
+use ethers::prelude::*;
+use ethers::types::Bytes;
+use ethers::utils::keccak256;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::utils::{unix_timestamp, write_private_file};
+
+use super::error::SecretsError;
+use super::indexer::{IndexedEvent, SpaceEvent};
+use super::space::SecretsSpace;
+
+/// Errors raised while reading or writing the local secrets cache.
+#[derive(Debug)]
+pub enum CacheError {
+    Io(std::io::Error),
+    /// An entry could not be encrypted or decrypted with the cache key.
+    Crypto(String),
+    /// An entry decrypted but its contents were not a valid cache record.
+    Corrupt(String),
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::Io(e) => write!(f, "cache io error: {}", e),
+            CacheError::Crypto(msg) => write!(f, "cache encryption error: {}", msg),
+            CacheError::Corrupt(msg) => write!(f, "corrupt cache entry: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+impl From<std::io::Error> for CacheError {
+    fn from(e: std::io::Error) -> Self {
+        CacheError::Io(e)
+    }
+}
+
+/// Identifies a cached secret. Versioned secrets are cached under their
+/// `VersionedIdentifier` bytes, so each version gets its own entry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub space: Address,
+    pub identifier: Bytes,
+}
+
+impl CacheKey {
+    pub fn new(space: Address, identifier: Bytes) -> Self {
+        Self { space, identifier }
+    }
+
+    /// The entry's file name, a hash of the identifier keyed with the
+    /// cache's name key so it can't be matched against guessed identifiers.
+    fn file_name(&self, name_key: &[u8; 32]) -> String {
+        let mut data = Vec::with_capacity(32 + self.identifier.len());
+        data.extend_from_slice(name_key);
+        data.extend_from_slice(&self.identifier);
+        format!("{}.cache", hex::encode(keccak256(data)))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheRecord {
+    stored_at: u64,
+    identifier: String,
+    value: String,
+}
+
+/// A secret read back from the cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedSecret {
+    pub value: Bytes,
+    pub stored_at: SystemTime,
+    /// Whether the entry is still within the cache TTL.
+    pub fresh: bool,
+}
+
+/// An on-disk cache of retrieved secrets, encrypted to the cache owner's key.
+///
+/// Entries live under `<dir>/<space address>/<keccak(name key, identifier)>.cache`
+/// and are ECIES-encrypted. Values and identifiers can't be recovered without
+/// the secret key, and since file names are keyed hashes they can't be
+/// checked against guessed identifiers either. The directory names are the
+/// plain space addresses, and the number, size and timestamps of entries are
+/// visible to anyone who can list the directory.
+pub struct SecretCache {
+    dir: PathBuf,
+    ttl: Duration,
+    public_key: PublicKey,
+    secret_key: SecretKey,
+    /// Derived from the secret key; keys the entry file names.
+    name_key: [u8; 32],
+}
+
+impl SecretCache {
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration, secret_key: SecretKey) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+            public_key: PublicKey::from_secret_key(&secret_key),
+            name_key: keccak256([b"swtch-cache-names".as_slice(), &secret_key.serialize()].concat()),
+            secret_key,
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    fn space_dir(&self, space: Address) -> PathBuf {
+        self.dir.join(format!("{:?}", space))
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.space_dir(key.space).join(key.file_name(&self.name_key))
+    }
+
+    /// Read an entry, returning stale entries with `fresh` set to false.
+    pub fn get(&self, key: &CacheKey) -> Result<Option<CachedSecret>, CacheError> {
+        let path = self.entry_path(key);
+        let sealed = match fs::read(&path) {
+            Ok(sealed) => sealed,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let plain = ecies::decrypt(&self.secret_key.serialize(), &sealed)
+            .map_err(|e| CacheError::Crypto(e.to_string()))?;
+        let record: CacheRecord =
+            serde_json::from_slice(&plain).map_err(|e| CacheError::Corrupt(e.to_string()))?;
+
+        // Guard against hash collisions and entries copied between spaces.
+        if record.identifier != hex::encode(&key.identifier) {
+            return Err(CacheError::Corrupt(format!("entry {} belongs to another identifier", path.display())));
+        }
+        let value = hex::decode(&record.value).map_err(|e| CacheError::Corrupt(e.to_string()))?;
+        let stored_at = UNIX_EPOCH + Duration::from_secs(record.stored_at);
+        let age = SystemTime::now().duration_since(stored_at).unwrap_or_default();
+        Ok(Some(CachedSecret {
+            value: Bytes::from(value),
+            stored_at,
+            fresh: age < self.ttl,
+        }))
+    }
+
+    pub fn put(&self, key: &CacheKey, value: &Bytes) -> Result<(), CacheError> {
+        let stored_at = unix_timestamp();
+        let record = CacheRecord {
+            stored_at,
+            identifier: hex::encode(&key.identifier),
+            value: hex::encode(value),
+        };
+        let plain = serde_json::to_vec(&record).map_err(|e| CacheError::Corrupt(e.to_string()))?;
+        let sealed = ecies::encrypt(&self.public_key.serialize(), &plain)
+            .map_err(|e| CacheError::Crypto(e.to_string()))?;
+
+        let dir = self.space_dir(key.space);
+        fs::create_dir_all(&dir)?;
+        let path = self.entry_path(key);
+        let tmp = path.with_extension("tmp");
+        write_private_file(&tmp, &sealed)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    pub fn invalidate(&self, key: &CacheKey) -> Result<(), CacheError> {
+        match fs::remove_file(self.entry_path(key)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Drop every cached entry for a space.
+    pub fn invalidate_space(&self, space: Address) -> Result<(), CacheError> {
+        match fs::remove_dir_all(self.space_dir(space)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Invalidate entries affected by on-chain changes to a space.
+    pub fn apply_events(&self, space: Address, events: &[IndexedEvent]) -> Result<(), CacheError> {
+        for indexed in events {
+            match &indexed.event {
+                SpaceEvent::SecretAdded { identifier }
+                | SpaceEvent::SecretDeleted { identifier }
+                | SpaceEvent::DelegateRevoked { identifier, .. } => {
+                    self.invalidate(&CacheKey::new(space, identifier.clone()))?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove entries older than the TTL. Returns the number of entries removed.
+    pub fn purge_expired(&self) -> Result<usize, CacheError> {
+        let mut removed = 0;
+        let spaces = match fs::read_dir(&self.dir) {
+            Ok(spaces) => spaces,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        for space in spaces {
+            let space = space?;
+            if !space.file_type()?.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(space.path())? {
+                let entry = entry?;
+                let age = entry
+                    .metadata()?
+                    .modified()
+                    .ok()
+                    .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                    .unwrap_or_default();
+                if age >= self.ttl {
+                    fs::remove_file(entry.path())?;
+                    removed += 1;
+                }
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Where a cached read was served from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadSource {
+    /// A fresh cache entry; no RPC call was made.
+    Cache,
+    /// The value was fetched from the chain and written to the cache, if
+    /// the write succeeded.
+    Network,
+    /// The RPC was unreachable and a stale cache entry was served.
+    Offline,
+}
+
+/// A `SecretsSpace` whose reads go through a `SecretCache`.
+pub struct CachedSecretsSpace<M: Middleware> {
+    space: SecretsSpace<M>,
+    cache: SecretCache,
+    offline_fallback: bool,
+}
+
+impl<M: Middleware + 'static> CachedSecretsSpace<M> {
+    pub fn new(space: SecretsSpace<M>, cache: SecretCache) -> Self {
+        Self { space, cache, offline_fallback: false }
+    }
+
+    /// Serve stale entries when the RPC can't be reached.
+    pub fn with_offline_fallback(mut self, enabled: bool) -> Self {
+        self.offline_fallback = enabled;
+        self
+    }
+
+    pub fn space(&self) -> &SecretsSpace<M> {
+        &self.space
+    }
+
+    pub fn cache(&self) -> &SecretCache {
+        &self.cache
+    }
+
+    pub async fn get_secret(&self, identifier: Bytes) -> Result<(Bytes, ReadSource), SecretsError<M>> {
+        let key = CacheKey::new(self.space.address(), identifier.clone());
+        // A bad entry must never block a network read; drop it and treat it as a miss.
+        let cached = self.cache.get(&key).unwrap_or_else(|e| {
+            log::warn!("discarding unreadable cache entry for {}: {}", identifier, e);
+            if let Err(e) = self.cache.invalidate(&key) {
+                log::warn!("failed to remove cache entry for {}: {}", identifier, e);
+            }
+            None
+        });
+        if let Some(entry) = &cached {
+            if entry.fresh {
+                return Ok((entry.value.clone(), ReadSource::Cache));
+            }
+        }
+
+        match self.space.get_secret(identifier).await {
+            Ok(value) => {
+                if let Err(e) = self.cache.put(&key, &value) {
+                    log::warn!("failed to cache {}: {}", key.identifier, e);
+                }
+                Ok((value, ReadSource::Network))
+            }
+            // Only transport failures fall back; reverts mean the chain answered.
+            Err(e @ ContractError::MiddlewareError { .. }) | Err(e @ ContractError::ProviderError { .. }) => {
+                match cached {
+                    Some(entry) if self.offline_fallback => Ok((entry.value, ReadSource::Offline)),
+                    _ => Err(e.into()),
+                }
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Invalidate cache entries touched by the given events.
+    pub fn apply_events(&self, events: &[IndexedEvent]) -> Result<(), SecretsError<M>> {
+        Ok(self.cache.apply_events(self.space.address(), events)?)
+    }
+}
@@ -0,0 +1,70 @@
+// src/secrets/error.rs
+
+use ethers::prelude::*;
+use std::fmt;
+
+use super::cache::CacheError;
+
+/// Errors returned by the higher level secrets workflows built on top of
+/// the raw `SecretsSpace` contract calls.
+#[derive(Debug)]
+pub enum SecretsError<M: Middleware> {
+    /// The underlying contract call or transaction failed.
+    Contract(ContractError<M>),
+    /// No version of the named secret has been written yet.
+    NoVersions(String),
+    /// The requested version does not exist or has been deleted.
+    VersionNotFound { name: String, version: u32 },
+    /// Old versions can't be retired until the grace period has elapsed.
+    GracePeriodActive { name: String, remaining: std::time::Duration },
+    /// The secret's embedded expiry has passed.
+    Expired { identifier: Bytes, expired_at: u64 },
+    /// The space fee is higher than the caller is willing to pay.
+    FeeExceedsMax { fee: U256, max_fee: U256 },
+    /// A withdrawal asked for more than the space has collected.
+    InsufficientFees { requested: U256, collected: U256 },
+    /// The local secrets cache could not be read or written.
+    Cache(CacheError),
+    /// An identifier or stored record could not be decoded.
+    InvalidRecord(String),
+}
+
+impl<M: Middleware> fmt::Display for SecretsError<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretsError::Contract(e) => write!(f, "contract error: {}", e),
+            SecretsError::NoVersions(name) => write!(f, "no versions found for secret '{}'", name),
+            SecretsError::VersionNotFound { name, version } => {
+                write!(f, "version {} of secret '{}' not found", version, name)
+            }
+            SecretsError::GracePeriodActive { name, remaining } => {
+                write!(f, "grace period for secret '{}' ends in {}s", name, remaining.as_secs())
+            }
+            SecretsError::Expired { identifier, expired_at } => {
+                write!(f, "secret {} expired at {}", identifier, expired_at)
+            }
+            SecretsError::FeeExceedsMax { fee, max_fee } => {
+                write!(f, "space fee {} exceeds maximum {}", fee, max_fee)
+            }
+            SecretsError::InsufficientFees { requested, collected } => {
+                write!(f, "requested {} but only {} fees collected", requested, collected)
+            }
+            SecretsError::Cache(e) => write!(f, "{}", e),
+            SecretsError::InvalidRecord(msg) => write!(f, "invalid record: {}", msg),
+        }
+    }
+}
+
+impl<M: Middleware> std::error::Error for SecretsError<M> {}
+
+impl<M: Middleware> From<ContractError<M>> for SecretsError<M> {
+    fn from(e: ContractError<M>) -> Self {
+        SecretsError::Contract(e)
+    }
+}
+
+impl<M: Middleware> From<CacheError> for SecretsError<M> {
+    fn from(e: CacheError) -> Self {
+        SecretsError::Cache(e)
+    }
+}
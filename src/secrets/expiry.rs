@@ -0,0 +1,298 @@
+// src/secrets/expiry.rs
+
+use ethers::prelude::*;
+use ethers::types::Bytes;
+use ethers::utils::keccak256;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use crate::utils::{unix_timestamp, write_private_file};
+
+use super::error::SecretsError;
+use super::space::SecretsSpace;
+
+/// Prefix marking a stored value that carries an expiry timestamp.
+const EXPIRING_MAGIC: &[u8; 3] = b"SWX";
+/// Envelope version, stored after the magic.
+const EXPIRING_VERSION: u8 = 2;
+/// Magic, version, expiry and value length.
+const EXPIRING_HEADER_LEN: usize = 3 + 1 + 8 + 4;
+/// Length of the truncated keccak tag that closes the envelope.
+const EXPIRING_TAG_LEN: usize = 8;
+
+/// A secret value wrapped with the time after which it must not be served.
+///
+/// Encoded as `SWX || 0x02 || expires_at (u64 BE) || len (u32 BE) || value ||
+/// keccak(everything before)[..8]`. Data is only read as wrapped if the
+/// version, length and tag all check out, so a plain value that happens to
+/// start with the magic is returned as-is. Values stored without the
+/// envelope never expire.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpiringSecret {
+    pub value: Bytes,
+    pub expires_at: Option<u64>,
+}
+
+impl ExpiringSecret {
+    pub fn new(value: Bytes, expires_at: u64) -> Self {
+        Self { value, expires_at: Some(expires_at) }
+    }
+
+    pub fn encode(&self) -> Bytes {
+        match self.expires_at {
+            Some(expires_at) => {
+                let mut data = Vec::with_capacity(EXPIRING_HEADER_LEN + self.value.len() + EXPIRING_TAG_LEN);
+                data.extend_from_slice(EXPIRING_MAGIC);
+                data.push(EXPIRING_VERSION);
+                data.extend_from_slice(&expires_at.to_be_bytes());
+                data.extend_from_slice(&(self.value.len() as u32).to_be_bytes());
+                data.extend_from_slice(&self.value);
+                let tag = keccak256(&data);
+                data.extend_from_slice(&tag[..EXPIRING_TAG_LEN]);
+                Bytes::from(data)
+            }
+            None => self.value.clone(),
+        }
+    }
+
+    pub fn decode(data: &[u8]) -> Self {
+        Self::unwrap(data).unwrap_or_else(|| Self { value: Bytes::from(data.to_vec()), expires_at: None })
+    }
+
+    fn unwrap(data: &[u8]) -> Option<Self> {
+        if data.len() < EXPIRING_HEADER_LEN + EXPIRING_TAG_LEN
+            || !data.starts_with(EXPIRING_MAGIC)
+            || data[3] != EXPIRING_VERSION
+        {
+            return None;
+        }
+        let expires_at = u64::from_be_bytes(data[4..12].try_into().ok()?);
+        let len = u32::from_be_bytes(data[12..16].try_into().ok()?) as usize;
+        if data.len() != EXPIRING_HEADER_LEN + len + EXPIRING_TAG_LEN {
+            return None;
+        }
+        let (body, tag) = data.split_at(EXPIRING_HEADER_LEN + len);
+        if keccak256(body)[..EXPIRING_TAG_LEN] != *tag {
+            return None;
+        }
+        Some(Self { value: Bytes::from(body[EXPIRING_HEADER_LEN..].to_vec()), expires_at: Some(expires_at) })
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+}
+
+/// A delegate grant that must be revoked at `expires_at`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrantExpiry {
+    pub delegate: Address,
+    pub identifier: Bytes,
+    pub expires_at: u64,
+}
+
+/// A secret that must be deleted at `expires_at`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretExpiry {
+    pub identifier: Bytes,
+    pub expires_at: u64,
+}
+
+/// The SDK-side record of every TTL set on a space. The contract has no notion
+/// of expiry, so this ledger is what the scheduler and the verification
+/// helpers consult.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpiryLedger {
+    pub grants: Vec<GrantExpiry>,
+    pub secrets: Vec<SecretExpiry>,
+}
+
+impl ExpiryLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a ledger from disk, returning an empty one if the file doesn't exist.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let data = serde_json::to_vec_pretty(self)?;
+        let tmp = path.with_extension("tmp");
+        write_private_file(&tmp, &data)?;
+        std::fs::rename(tmp, path)
+    }
+
+    /// Record a grant expiry, replacing any earlier expiry for the same grant.
+    pub fn record_grant(&mut self, delegate: Address, identifier: Bytes, expires_at: u64) {
+        self.grants.retain(|g| !(g.delegate == delegate && g.identifier == identifier));
+        self.grants.push(GrantExpiry { delegate, identifier, expires_at });
+    }
+
+    /// Record a secret expiry, replacing any earlier expiry for the same identifier.
+    pub fn record_secret(&mut self, identifier: Bytes, expires_at: u64) {
+        self.secrets.retain(|s| s.identifier != identifier);
+        self.secrets.push(SecretExpiry { identifier, expires_at });
+    }
+
+    /// Whether a delegate may still be served a secret. Grants without a
+    /// recorded TTL are treated as active.
+    pub fn is_grant_active(&self, delegate: Address, identifier: &[u8], now: u64) -> bool {
+        self.grants
+            .iter()
+            .find(|g| g.delegate == delegate && g.identifier.as_ref() == identifier)
+            .is_none_or(|g| now < g.expires_at)
+    }
+
+    pub fn due_grants(&self, now: u64) -> Vec<GrantExpiry> {
+        self.grants.iter().filter(|g| now >= g.expires_at).cloned().collect()
+    }
+
+    pub fn due_secrets(&self, now: u64) -> Vec<SecretExpiry> {
+        self.secrets.iter().filter(|s| now >= s.expires_at).cloned().collect()
+    }
+}
+
+/// What one scheduler pass cleaned up.
+#[derive(Debug, Clone, Default)]
+pub struct CleanupReport {
+    pub revoked: Vec<GrantExpiry>,
+    pub deleted: Vec<SecretExpiry>,
+    /// Entries whose transaction failed; they stay in the ledger for the next pass.
+    pub failed: Vec<String>,
+}
+
+impl<M: Middleware + 'static> SecretsSpace<M> {
+    /// Store a secret that expires after `ttl` and record it in the ledger.
+    pub async fn add_expiring_secret(
+        &self,
+        identifier: Bytes,
+        secret_value: Bytes,
+        ttl: Duration,
+        ledger: &mut ExpiryLedger,
+        max_fee: Option<U256>,
+    ) -> Result<TransactionReceipt, SecretsError<M>> {
+        let expires_at = unix_timestamp() + ttl.as_secs();
+        let wrapped = ExpiringSecret::new(secret_value, expires_at).encode();
+        let receipt = self.add_secret_paid(identifier.clone(), wrapped, max_fee).await?;
+        ledger.record_secret(identifier, expires_at);
+        Ok(receipt)
+    }
+
+    /// Authorize a delegate for `ttl` and record the grant in the ledger.
+    pub async fn authorize_delegate_for(
+        &self,
+        delegate: Address,
+        identifier: Bytes,
+        ttl: Duration,
+        ledger: &mut ExpiryLedger,
+    ) -> Result<TransactionReceipt, SecretsError<M>> {
+        let receipt = self.authorize_delegate(delegate, identifier.clone()).await?;
+        ledger.record_grant(delegate, identifier, unix_timestamp() + ttl.as_secs());
+        Ok(receipt)
+    }
+
+    /// Read a secret, refusing to return it once its embedded expiry has
+    /// passed, even if it hasn't been deleted on-chain yet.
+    pub async fn get_unexpired_secret(&self, identifier: Bytes) -> Result<Bytes, SecretsError<M>> {
+        let secret = ExpiringSecret::decode(&self.get_secret(identifier.clone()).await?);
+        if secret.is_expired(unix_timestamp()) {
+            return Err(SecretsError::Expired {
+                identifier,
+                expired_at: secret.expires_at.unwrap_or_default(),
+            });
+        }
+        Ok(secret.value)
+    }
+}
+
+/// Revokes expired grants and deletes expired secrets recorded in an `ExpiryLedger`.
+pub struct ExpiryScheduler<M: Middleware> {
+    space: SecretsSpace<M>,
+    ledger: Arc<Mutex<ExpiryLedger>>,
+    ledger_path: Option<PathBuf>,
+}
+
+impl<M: Middleware + 'static> ExpiryScheduler<M> {
+    pub fn new(space: SecretsSpace<M>, ledger: Arc<Mutex<ExpiryLedger>>) -> Self {
+        Self { space, ledger, ledger_path: None }
+    }
+
+    /// Persist the ledger to `path` after every pass.
+    pub fn with_ledger_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ledger_path = Some(path.into());
+        self
+    }
+
+    pub fn ledger(&self) -> Arc<Mutex<ExpiryLedger>> {
+        Arc::clone(&self.ledger)
+    }
+
+    /// Process everything due at `now`.
+    pub async fn run_once(&self, now: u64) -> CleanupReport {
+        let (due_grants, due_secrets) = {
+            let ledger = self.ledger.lock().await;
+            (ledger.due_grants(now), ledger.due_secrets(now))
+        };
+
+        let mut report = CleanupReport::default();
+        for grant in due_grants {
+            match self.space.revoke_delegate(grant.delegate, grant.identifier.clone()).await {
+                Ok(_) => report.revoked.push(grant),
+                Err(e) => report.failed.push(format!("revoke {:?}: {}", grant.delegate, e)),
+            }
+        }
+        for secret in due_secrets {
+            match self.space.delete_secret(secret.identifier.clone()).await {
+                Ok(_) => report.deleted.push(secret),
+                Err(e) => report.failed.push(format!("delete {}: {}", secret.identifier, e)),
+            }
+        }
+
+        let mut ledger = self.ledger.lock().await;
+        ledger.grants.retain(|g| !report.revoked.contains(g));
+        ledger.secrets.retain(|s| !report.deleted.contains(s));
+        // Grants on a deleted secret can't be revoked any more.
+        ledger
+            .grants
+            .retain(|g| !report.deleted.iter().any(|s| s.identifier == g.identifier));
+        if let Some(path) = &self.ledger_path {
+            if let Err(e) = ledger.save(path) {
+                report.failed.push(format!("save ledger: {}", e));
+            }
+        }
+        report
+    }
+
+    /// Run a pass every `interval` until the returned handle is aborted.
+    /// Cleanups are logged at info level and failures at warn level; use
+    /// `run_once` to handle reports directly.
+    pub fn spawn(self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let report = self.run_once(unix_timestamp()).await;
+                if !report.revoked.is_empty() || !report.deleted.is_empty() {
+                    log::info!(
+                        "expiry scheduler revoked {} grants and deleted {} secrets",
+                        report.revoked.len(),
+                        report.deleted.len()
+                    );
+                }
+                for failure in &report.failed {
+                    log::warn!("expiry scheduler: {}", failure);
+                }
+            }
+        })
+    }
+}
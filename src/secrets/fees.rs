@@ -0,0 +1,83 @@
+// src/secrets/fees.rs
+
+use ethers::prelude::*;
+use ethers::types::Bytes;
+
+use super::error::SecretsError;
+use super::space::SecretsSpace;
+
+/// Fee accounting for a space, as seen by its owner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeSummary {
+    /// The fee currently charged per `add_secret`.
+    pub fee: U256,
+    /// Fees collected and not yet withdrawn.
+    pub collected: U256,
+    /// The ETH balance held by the space contract.
+    pub balance: U256,
+}
+
+impl<M: Middleware + 'static> SecretsSpace<M> {
+    /// Query the current fee, refusing it if it exceeds `max_fee`.
+    pub async fn quote_fee(&self, max_fee: Option<U256>) -> Result<U256, SecretsError<M>> {
+        let fee = self.get_fee().await?;
+        match max_fee {
+            Some(max_fee) if fee > max_fee => Err(SecretsError::FeeExceedsMax { fee, max_fee }),
+            _ => Ok(fee),
+        }
+    }
+
+    /// Add a secret, attaching the current fee automatically.
+    ///
+    /// If the transaction reverts because the fee changed between the quote
+    /// and the send, the fee is quoted again and the write retried once as
+    /// long as the new fee is still within `max_fee`.
+    pub async fn add_secret_paid(
+        &self,
+        identifier: Bytes,
+        secret_value: Bytes,
+        max_fee: Option<U256>,
+    ) -> Result<TransactionReceipt, SecretsError<M>> {
+        let fee = self.quote_fee(max_fee).await?;
+        match self.add_secret(identifier.clone(), secret_value.clone(), fee).await {
+            Ok(receipt) => Ok(receipt),
+            Err(ContractError::Revert(data)) => {
+                let current = self.quote_fee(max_fee).await?;
+                if current == fee {
+                    return Err(ContractError::Revert(data).into());
+                }
+                Ok(self.add_secret(identifier, secret_value, current).await?)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn fee_summary(&self) -> Result<FeeSummary, SecretsError<M>> {
+        let fee = self.get_fee().await?;
+        let collected = self.fees_collected().await?;
+        let balance = self
+            .client()
+            .get_balance(self.address(), None)
+            .await
+            .map_err(ContractError::from_middleware_error)?;
+        Ok(FeeSummary { fee, collected, balance })
+    }
+
+    /// Withdraw `amount`, checking first that the space has collected enough.
+    pub async fn withdraw_fees_checked(&self, recipient: Address, amount: U256) -> Result<TransactionReceipt, SecretsError<M>> {
+        let collected = self.fees_collected().await?;
+        if amount > collected {
+            return Err(SecretsError::InsufficientFees { requested: amount, collected });
+        }
+        Ok(self.withdraw_fees(recipient, amount).await?)
+    }
+
+    /// Withdraw everything collected so far. Returns `None` if there was nothing to withdraw.
+    pub async fn withdraw_all_fees(&self, recipient: Address) -> Result<Option<TransactionReceipt>, SecretsError<M>> {
+        let collected = self.fees_collected().await?;
+        if collected.is_zero() {
+            return Ok(None);
+        }
+        Ok(Some(self.withdraw_fees(recipient, collected).await?))
+    }
+}
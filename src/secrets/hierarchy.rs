@@ -0,0 +1,213 @@
+// src/secrets/hierarchy.rs
+
+use ethers::prelude::*;
+use ethers::types::Bytes;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::error::SecretsError;
+use super::manager::SecretsManager;
+
+/// A user's space and, recursively, the spaces of its sub-users.
+///
+/// `getSubSpaces(userDID)` returns the DIDs of the sub-users registered via
+/// `addSubSpace`, and each of those resolves to its own space with `getSpace`.
+/// The manager reports a zero address for users without an active space.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpaceNode {
+    /// The DID that owns this space.
+    pub did: Address,
+    pub space: Address,
+    /// Set when the user has no active space or the space rejects calls.
+    pub disabled: bool,
+    /// The space fee, if the space could be queried.
+    pub fee: Option<U256>,
+    pub children: Vec<SpaceNode>,
+}
+
+/// A flattened status line for one node of a `SpaceNode` tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpaceStatus {
+    pub did: Address,
+    pub parent: Option<Address>,
+    pub depth: usize,
+    pub space: Address,
+    pub disabled: bool,
+    pub fee: Option<U256>,
+}
+
+impl SpaceNode {
+    /// Find the node owned by `did`.
+    pub fn find(&self, did: Address) -> Option<&SpaceNode> {
+        if self.did == did {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(did))
+    }
+
+    /// The chain of nodes from the root down to `did`, inclusive.
+    pub fn path_to(&self, did: Address) -> Option<Vec<&SpaceNode>> {
+        if self.did == did {
+            return Some(vec![self]);
+        }
+        self.children.iter().find_map(|child| {
+            child.path_to(did).map(|mut path| {
+                path.insert(0, self);
+                path
+            })
+        })
+    }
+
+    /// Status of every node, depth first, with the root at depth 0.
+    pub fn report(&self) -> Vec<SpaceStatus> {
+        let mut report = Vec::new();
+        self.collect_status(None, 0, &mut report);
+        report
+    }
+
+    fn collect_status(&self, parent: Option<Address>, depth: usize, report: &mut Vec<SpaceStatus>) {
+        report.push(SpaceStatus {
+            did: self.did,
+            parent,
+            depth,
+            space: self.space,
+            disabled: self.disabled,
+            fee: self.fee,
+        });
+        for child in &self.children {
+            child.collect_status(Some(self.did), depth + 1, report);
+        }
+    }
+}
+
+/// Which spaces `find_secret` searches besides the starting one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inheritance {
+    /// Only the starting space.
+    None,
+    /// The starting space, then its parent, up to the root. Sub-spaces inherit
+    /// secrets defined higher up the tree.
+    Ancestors,
+    /// The starting space, then its sub-spaces breadth first.
+    Descendants,
+}
+
+/// A secret found by `find_secret`, with the space it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretLookup {
+    pub did: Address,
+    pub space: Address,
+    pub value: Bytes,
+}
+
+struct NodeInfo {
+    space: Address,
+    disabled: bool,
+    fee: Option<U256>,
+    children: Vec<Address>,
+}
+
+impl<M: Middleware + 'static> SecretsManager<M> {
+    /// Build the tree of spaces rooted at `user_did`, descending at most
+    /// `max_depth` levels. DIDs that appear twice are only expanded once.
+    pub async fn space_tree(&self, user_did: Address, max_depth: usize) -> Result<SpaceNode, SecretsError<M>> {
+        let mut nodes: HashMap<Address, NodeInfo> = HashMap::new();
+        let mut queue = VecDeque::from([(user_did, 0usize)]);
+
+        while let Some((did, depth)) = queue.pop_front() {
+            if nodes.contains_key(&did) {
+                continue;
+            }
+            let space = self.get_space(did).await?;
+            let (disabled, fee) = if space.is_zero() {
+                (true, None)
+            } else {
+                match self.get_secrets_space(space).await.get_fee().await {
+                    Ok(fee) => (false, Some(fee)),
+                    Err(ContractError::Revert(_)) => (true, None),
+                    Err(e) => return Err(e.into()),
+                }
+            };
+            let children = if depth < max_depth {
+                self.get_sub_spaces(did).await?
+            } else {
+                Vec::new()
+            };
+            for child in &children {
+                queue.push_back((*child, depth + 1));
+            }
+            nodes.insert(did, NodeInfo { space, disabled, fee, children });
+        }
+
+        let mut expanded = HashSet::from([user_did]);
+        Ok(assemble(user_did, &nodes, &mut expanded))
+    }
+
+    /// Look up `identifier` starting at `start_did`'s space and following the
+    /// inheritance rule through `tree`. Spaces that revert (missing
+    /// identifier or no access) are skipped.
+    pub async fn find_secret(
+        &self,
+        tree: &SpaceNode,
+        start_did: Address,
+        identifier: Bytes,
+        inheritance: Inheritance,
+    ) -> Result<Option<SecretLookup>, SecretsError<M>> {
+        let candidates: Vec<&SpaceNode> = match inheritance {
+            Inheritance::None => tree.find(start_did).into_iter().collect(),
+            Inheritance::Ancestors => tree
+                .path_to(start_did)
+                .map(|path| path.into_iter().rev().collect())
+                .unwrap_or_default(),
+            Inheritance::Descendants => {
+                let mut order = Vec::new();
+                let mut queue: VecDeque<&SpaceNode> = tree.find(start_did).into_iter().collect();
+                while let Some(node) = queue.pop_front() {
+                    order.push(node);
+                    queue.extend(node.children.iter());
+                }
+                order
+            }
+        };
+
+        for node in candidates.into_iter().filter(|node| !node.disabled) {
+            let space = self.get_secrets_space(node.space).await;
+            match space.get_secret(identifier.clone()).await {
+                Ok(value) if !value.is_empty() => {
+                    return Ok(Some(SecretLookup { did: node.did, space: node.space, value }));
+                }
+                Ok(_) | Err(ContractError::Revert(_)) => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Disable every active space in `tree`, sub-spaces before their parents.
+    pub async fn disable_tree(&self, tree: &SpaceNode) -> Result<Vec<TransactionReceipt>, SecretsError<M>> {
+        let mut order = tree.report();
+        order.sort_by_key(|status| std::cmp::Reverse(status.depth));
+
+        let mut receipts = Vec::new();
+        for status in order.into_iter().filter(|status| !status.disabled) {
+            receipts.push(self.disable_space(status.did).await?);
+        }
+        Ok(receipts)
+    }
+}
+
+fn assemble(did: Address, nodes: &HashMap<Address, NodeInfo>, expanded: &mut HashSet<Address>) -> SpaceNode {
+    let info = &nodes[&did];
+    let mut children = Vec::new();
+    for child in &info.children {
+        if nodes.contains_key(child) && expanded.insert(*child) {
+            children.push(assemble(*child, nodes, expanded));
+        }
+    }
+    SpaceNode {
+        did,
+        space: info.space,
+        disabled: info.disabled,
+        fee: info.fee,
+        children,
+    }
+}
@@ -0,0 +1,253 @@
+// src/secrets/indexer.rs
+
+use ethers::prelude::*;
+use ethers::types::Bytes;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use super::error::SecretsError;
+use super::space::{SecretsSpace, SecretsSpaceContractEvents};
+
+/// Default number of blocks requested per `eth_getLogs` call.
+pub const DEFAULT_BLOCK_RANGE: u64 = 5_000;
+
+/// A state change emitted by a `SecretsSpace` contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpaceEvent {
+    SecretAdded { identifier: Bytes },
+    SecretDeleted { identifier: Bytes },
+    DelegateAuthorized { delegate: Address, identifier: Bytes },
+    DelegateRevoked { delegate: Address, identifier: Bytes },
+    FeeAdjusted { fee: U256 },
+    FeesWithdrawn { recipient: Address, amount: U256 },
+}
+
+impl From<SecretsSpaceContractEvents> for SpaceEvent {
+    fn from(event: SecretsSpaceContractEvents) -> Self {
+        match event {
+            SecretsSpaceContractEvents::SecretAddedFilter(e) => SpaceEvent::SecretAdded { identifier: e.identifier },
+            SecretsSpaceContractEvents::SecretDeletedFilter(e) => SpaceEvent::SecretDeleted { identifier: e.identifier },
+            SecretsSpaceContractEvents::DelegateAuthorizedFilter(e) => SpaceEvent::DelegateAuthorized {
+                delegate: e.delegate,
+                identifier: e.identifier,
+            },
+            SecretsSpaceContractEvents::DelegateRevokedFilter(e) => SpaceEvent::DelegateRevoked {
+                delegate: e.delegate,
+                identifier: e.identifier,
+            },
+            SecretsSpaceContractEvents::FeesAdjustedFilter(e) => SpaceEvent::FeeAdjusted { fee: e.new_fee },
+            SecretsSpaceContractEvents::FeesWithdrawnFilter(e) => SpaceEvent::FeesWithdrawn {
+                recipient: e.recipient,
+                amount: e.amount,
+            },
+        }
+    }
+}
+
+/// A `SpaceEvent` together with where it was emitted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedEvent {
+    pub block_number: u64,
+    pub transaction_hash: H256,
+    pub log_index: U256,
+    pub event: SpaceEvent,
+}
+
+/// The current state of a space as reconstructed from its events.
+#[derive(Debug, Clone, Default)]
+pub struct SpaceIndex {
+    identifiers: BTreeSet<Bytes>,
+    grants: BTreeMap<Bytes, BTreeSet<Address>>,
+    deleted: BTreeSet<Bytes>,
+    fee: Option<U256>,
+    last_block: Option<u64>,
+}
+
+impl SpaceIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply an event. Events must be applied in chain order.
+    pub fn apply(&mut self, indexed: &IndexedEvent) {
+        match &indexed.event {
+            SpaceEvent::SecretAdded { identifier } => {
+                self.deleted.remove(identifier);
+                self.identifiers.insert(identifier.clone());
+            }
+            SpaceEvent::SecretDeleted { identifier } => {
+                self.identifiers.remove(identifier);
+                self.grants.remove(identifier);
+                self.deleted.insert(identifier.clone());
+            }
+            SpaceEvent::DelegateAuthorized { delegate, identifier } => {
+                self.grants.entry(identifier.clone()).or_default().insert(*delegate);
+            }
+            SpaceEvent::DelegateRevoked { delegate, identifier } => {
+                if let Some(delegates) = self.grants.get_mut(identifier) {
+                    delegates.remove(delegate);
+                    if delegates.is_empty() {
+                        self.grants.remove(identifier);
+                    }
+                }
+            }
+            SpaceEvent::FeeAdjusted { fee } => self.fee = Some(*fee),
+            SpaceEvent::FeesWithdrawn { .. } => {}
+        }
+        self.last_block = Some(self.last_block.map_or(indexed.block_number, |b| b.max(indexed.block_number)));
+    }
+
+    /// Identifiers currently stored in the space.
+    pub fn identifiers(&self) -> impl Iterator<Item = &Bytes> {
+        self.identifiers.iter()
+    }
+
+    pub fn contains(&self, identifier: &[u8]) -> bool {
+        self.identifiers.contains(identifier)
+    }
+
+    /// Identifiers that were deleted and not re-added since.
+    pub fn deleted(&self) -> impl Iterator<Item = &Bytes> {
+        self.deleted.iter()
+    }
+
+    /// Delegates currently authorized for an identifier.
+    pub fn delegates(&self, identifier: &[u8]) -> Vec<Address> {
+        self.grants
+            .get(identifier)
+            .map(|delegates| delegates.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Identifiers a delegate is currently authorized for.
+    pub fn grants_for(&self, delegate: Address) -> Vec<Bytes> {
+        self.grants
+            .iter()
+            .filter(|(_, delegates)| delegates.contains(&delegate))
+            .map(|(identifier, _)| identifier.clone())
+            .collect()
+    }
+
+    /// The last fee set through `adjustFees`, if any was observed.
+    pub fn fee(&self) -> Option<U256> {
+        self.fee
+    }
+
+    /// The highest block an event was applied from.
+    pub fn last_block(&self) -> Option<u64> {
+        self.last_block
+    }
+}
+
+/// Scans a space contract's logs and keeps a `SpaceIndex` up to date.
+pub struct SpaceIndexer<M: Middleware> {
+    space: SecretsSpace<M>,
+    index: SpaceIndex,
+    next_block: u64,
+    block_range: u64,
+}
+
+impl<M: Middleware + 'static> SpaceIndexer<M> {
+    /// Create an indexer that starts scanning at `from_block`, typically the
+    /// block the space was deployed in.
+    pub fn new(space: SecretsSpace<M>, from_block: u64) -> Self {
+        Self {
+            space,
+            index: SpaceIndex::new(),
+            next_block: from_block,
+            block_range: DEFAULT_BLOCK_RANGE,
+        }
+    }
+
+    pub fn with_block_range(mut self, block_range: u64) -> Self {
+        self.block_range = block_range.max(1);
+        self
+    }
+
+    pub fn index(&self) -> &SpaceIndex {
+        &self.index
+    }
+
+    pub fn space(&self) -> &SecretsSpace<M> {
+        &self.space
+    }
+
+    /// Fetch events up to the latest block, apply them and return them.
+    /// The index and cursor only move once every chunk was fetched, so a
+    /// failed fetch leaves the indexer as it was and the next sync retries
+    /// the whole range.
+    pub async fn sync(&mut self) -> Result<Vec<IndexedEvent>, SecretsError<M>> {
+        let latest = self
+            .space
+            .client()
+            .get_block_number()
+            .await
+            .map_err(ContractError::from_middleware_error)?
+            .as_u64();
+
+        let mut new_events = Vec::new();
+        let mut next_block = self.next_block;
+        while next_block <= latest {
+            let to_block = latest.min(next_block + self.block_range - 1);
+            let mut events = self.fetch(next_block, to_block).await?;
+            events.sort_by_key(|e| (e.block_number, e.log_index));
+            new_events.extend(events);
+            next_block = to_block + 1;
+        }
+        for event in &new_events {
+            self.index.apply(event);
+        }
+        self.next_block = next_block;
+        Ok(new_events)
+    }
+
+    async fn fetch(&self, from_block: u64, to_block: u64) -> Result<Vec<IndexedEvent>, SecretsError<M>> {
+        let logs = self
+            .space
+            .contract()
+            .events()
+            .from_block(from_block)
+            .to_block(to_block)
+            .query_with_meta()
+            .await?;
+        Ok(logs
+            .into_iter()
+            .map(|(event, meta)| IndexedEvent {
+                block_number: meta.block_number.as_u64(),
+                transaction_hash: meta.transaction_hash,
+                log_index: meta.log_index,
+                event: event.into(),
+            })
+            .collect())
+    }
+
+    /// Poll for new events every `interval` and stream them to the returned
+    /// receiver. Failed polls are logged and retried on the next tick; the
+    /// task stops when the receiver is dropped.
+    pub fn watch(mut self, interval: Duration) -> mpsc::Receiver<IndexedEvent> {
+        let (sender, receiver) = mpsc::channel(256);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if sender.is_closed() {
+                    return;
+                }
+                let events = match self.sync().await {
+                    Ok(events) => events,
+                    Err(e) => {
+                        log::warn!("space indexer sync failed: {}", e);
+                        continue;
+                    }
+                };
+                for event in events {
+                    if sender.send(event).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        receiver
+    }
+}
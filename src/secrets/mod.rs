@@ -1,5 +1,21 @@
+mod backup;
+mod cache;
+mod error;
+mod expiry;
+mod fees;
+mod hierarchy;
+mod indexer;
 mod manager;
 mod space;
+mod versioning;
 
+pub use backup::{BackupArchive, BackupDiff, BackupEntry, BackupError, BackupManifest, ExportSource, ImportReport};
+pub use cache::{CacheError, CacheKey, CachedSecret, CachedSecretsSpace, ReadSource, SecretCache};
+pub use error::SecretsError;
+pub use expiry::{CleanupReport, ExpiringSecret, ExpiryLedger, ExpiryScheduler, GrantExpiry, SecretExpiry};
+pub use fees::FeeSummary;
+pub use hierarchy::{Inheritance, SecretLookup, SpaceNode, SpaceStatus};
+pub use indexer::{IndexedEvent, SpaceEvent, SpaceIndex, SpaceIndexer};
 pub use manager::SecretsManager;
-pub use space::SecretsSpace;
\ No newline at end of file
+pub use space::SecretsSpace;
+pub use versioning::{Rotation, RotationPolicy, VersionHead, VersionedIdentifier};
\ No newline at end of file
@@ -16,6 +16,12 @@ abigen!(
         function authorizeDelegate(address delegate, bytes memory identifier) public
         function revokeDelegate(address delegate, bytes memory identifier) public
         function deleteSecret(bytes memory identifier) public
+        event SecretAdded(bytes identifier)
+        event SecretDeleted(bytes identifier)
+        event DelegateAuthorized(address indexed delegate, bytes identifier)
+        event DelegateRevoked(address indexed delegate, bytes identifier)
+        event FeesAdjusted(uint256 newFee)
+        event FeesWithdrawn(address indexed recipient, uint256 amount)
     ]"#,
 );
 
@@ -29,6 +35,18 @@ impl<M: Middleware + 'static> SecretsSpace<M> {
         Self { secrets_space }
     }
 
+    pub fn address(&self) -> Address {
+        self.secrets_space.address()
+    }
+
+    pub(crate) fn client(&self) -> Arc<M> {
+        self.secrets_space.client()
+    }
+
+    pub(super) fn contract(&self) -> &SecretsSpaceContract<M> {
+        &self.secrets_space
+    }
+
     pub async fn get_fee(&self) -> Result<U256, ContractError<M>> {
         self.secrets_space.get_fee().call().await
     }
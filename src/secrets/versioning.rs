@@ -0,0 +1,315 @@
+// src/secrets/versioning.rs
+
+use ethers::prelude::*;
+use ethers::types::Bytes;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use super::error::SecretsError;
+use super::space::SecretsSpace;
+
+/// Separator between a secret name and its version number, e.g. `db-password#v3`.
+pub const VERSION_SEPARATOR: &str = "#v";
+
+/// Suffix of the record that tracks the latest and oldest retained versions.
+pub const HEAD_SUFFIX: &str = "#head";
+
+/// A secret name paired with a version, encoded on-chain as `<name>#v<version>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VersionedIdentifier {
+    pub name: String,
+    pub version: u32,
+}
+
+impl VersionedIdentifier {
+    pub fn new(name: &str, version: u32) -> Self {
+        Self { name: name.to_string(), version }
+    }
+
+    /// Parse an on-chain identifier. Returns `None` for unversioned identifiers.
+    pub fn parse(identifier: &[u8]) -> Option<Self> {
+        let identifier = std::str::from_utf8(identifier).ok()?;
+        let (name, version) = identifier.rsplit_once(VERSION_SEPARATOR)?;
+        if name.is_empty() || version.is_empty() || !version.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        Some(Self::new(name, version.parse().ok()?))
+    }
+
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(self.to_string().into_bytes())
+    }
+
+    /// The identifier of the head record for a secret name.
+    pub fn head(name: &str) -> Bytes {
+        Bytes::from(format!("{}{}", name, HEAD_SUFFIX).into_bytes())
+    }
+}
+
+impl fmt::Display for VersionedIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.name, VERSION_SEPARATOR, self.version)
+    }
+}
+
+/// Latest and oldest retained versions of a secret and the delegates
+/// authorized on it, stored under `<name>#head`. The delegates are carried
+/// over to every new version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionHead {
+    pub latest: u32,
+    pub oldest: u32,
+    pub delegates: Vec<Address>,
+}
+
+impl VersionHead {
+    pub fn encode(&self) -> Bytes {
+        let mut data = Vec::with_capacity(8 + 20 * self.delegates.len());
+        data.extend_from_slice(&self.latest.to_be_bytes());
+        data.extend_from_slice(&self.oldest.to_be_bytes());
+        for delegate in &self.delegates {
+            data.extend_from_slice(delegate.as_bytes());
+        }
+        Bytes::from(data)
+    }
+
+    /// Decode a head record. Records written before delegates were tracked
+    /// are the 8-byte version pair alone and decode with no delegates.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None;
+        }
+        let delegates = data[8..].chunks_exact(20);
+        if !delegates.remainder().is_empty() {
+            return None;
+        }
+        let latest = u32::from_be_bytes(data[0..4].try_into().ok()?);
+        let oldest = u32::from_be_bytes(data[4..8].try_into().ok()?);
+        if oldest == 0 || oldest > latest {
+            return None;
+        }
+        Some(Self { latest, oldest, delegates: delegates.map(Address::from_slice).collect() })
+    }
+
+    /// Versions that are still stored on-chain, oldest first.
+    pub fn retained(&self) -> std::ops::RangeInclusive<u32> {
+        self.oldest..=self.latest
+    }
+}
+
+/// How `rotate_secret` treats delegates and previous versions.
+#[derive(Debug, Clone, Default)]
+pub struct RotationPolicy {
+    /// Delegates to authorize on the new version and the head record, in
+    /// addition to those recorded for the previous version.
+    pub delegates: Vec<Address>,
+    /// Delete previous versions once this much time has passed. `None` keeps
+    /// them indefinitely and `Some(Duration::ZERO)` deletes them immediately.
+    pub grace_period: Option<Duration>,
+    /// Refuse to write if the space fee rises above this amount.
+    pub max_fee: Option<U256>,
+}
+
+/// The outcome of a rotation.
+#[derive(Debug, Clone)]
+pub struct Rotation {
+    pub name: String,
+    pub version: u32,
+    /// Versions scheduled for deletion once `retire_after` has passed.
+    pub pending_retirement: Vec<u32>,
+    pub retire_after: Option<SystemTime>,
+    /// The policy's fee cap, applied again when the versions are retired.
+    pub max_fee: Option<U256>,
+    pub receipts: Vec<TransactionReceipt>,
+}
+
+impl<M: Middleware + 'static> SecretsSpace<M> {
+    /// Read the head record of a secret, or `None` if it was never versioned.
+    pub async fn version_head(&self, name: &str) -> Result<Option<VersionHead>, SecretsError<M>> {
+        let data = match self.get_secret(VersionedIdentifier::head(name)).await {
+            Ok(data) => data,
+            // A missing identifier reverts on-chain.
+            Err(ContractError::Revert(_)) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if data.is_empty() {
+            return Ok(None);
+        }
+        VersionHead::decode(&data)
+            .map(Some)
+            .ok_or_else(|| SecretsError::InvalidRecord(format!("malformed head record for '{}'", name)))
+    }
+
+    pub async fn latest_version(&self, name: &str) -> Result<Option<u32>, SecretsError<M>> {
+        Ok(self.version_head(name).await?.map(|head| head.latest))
+    }
+
+    pub async fn get_secret_version(&self, name: &str, version: u32) -> Result<Bytes, SecretsError<M>> {
+        let identifier = VersionedIdentifier::new(name, version);
+        match self.get_secret(identifier.to_bytes()).await {
+            Ok(data) if !data.is_empty() => Ok(data),
+            Ok(_) | Err(ContractError::Revert(_)) => Err(SecretsError::VersionNotFound {
+                name: name.to_string(),
+                version,
+            }),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Fetch the latest version of a secret along with its version number.
+    pub async fn get_latest_secret(&self, name: &str) -> Result<(u32, Bytes), SecretsError<M>> {
+        let version = self
+            .latest_version(name)
+            .await?
+            .ok_or_else(|| SecretsError::NoVersions(name.to_string()))?;
+        let secret = self.get_secret_version(name, version).await?;
+        Ok((version, secret))
+    }
+
+    /// Write a new version of a secret, re-authorize the previous version's
+    /// delegates and the policy's delegates on it, and schedule or perform
+    /// deletion of previous versions.
+    pub async fn rotate_secret(
+        &self,
+        name: &str,
+        secret_value: Bytes,
+        policy: &RotationPolicy,
+    ) -> Result<Rotation, SecretsError<M>> {
+        let previous = self.version_head(name).await?;
+        let mut delegates = previous.as_ref().map(|head| head.delegates.clone()).unwrap_or_default();
+        for delegate in &policy.delegates {
+            if !delegates.contains(delegate) {
+                delegates.push(*delegate);
+            }
+        }
+        let head = match &previous {
+            Some(head) => VersionHead { latest: head.latest + 1, oldest: head.oldest, delegates },
+            None => VersionHead { latest: 1, oldest: 1, delegates },
+        };
+        let identifier = VersionedIdentifier::new(name, head.latest);
+        let head_identifier = VersionedIdentifier::head(name);
+
+        let mut receipts = Vec::new();
+        receipts.push(self.add_secret_paid(identifier.to_bytes(), secret_value, policy.max_fee).await?);
+        receipts.push(self.add_secret_paid(head_identifier.clone(), head.encode(), policy.max_fee).await?);
+
+        for delegate in &head.delegates {
+            receipts.push(self.authorize_delegate(*delegate, identifier.to_bytes()).await?);
+            receipts.push(self.authorize_delegate(*delegate, head_identifier.clone()).await?);
+        }
+
+        let mut rotation = Rotation {
+            name: name.to_string(),
+            version: head.latest,
+            pending_retirement: Vec::new(),
+            retire_after: None,
+            max_fee: policy.max_fee,
+            receipts,
+        };
+
+        if let (Some(grace_period), Some(previous)) = (policy.grace_period, &previous) {
+            rotation.pending_retirement = previous.retained().collect();
+            rotation.retire_after = Some(SystemTime::now() + grace_period);
+            if grace_period.is_zero() {
+                let pruned = self.prune_versions(name, head.latest, policy.max_fee).await?;
+                rotation.receipts.extend(pruned);
+                rotation.pending_retirement.clear();
+            }
+        }
+
+        Ok(rotation)
+    }
+
+    /// Authorize a delegate on the latest version and the head record, and
+    /// record it so later rotations carry the grant over.
+    pub async fn authorize_versioned_delegate(
+        &self,
+        name: &str,
+        delegate: Address,
+        max_fee: Option<U256>,
+    ) -> Result<Vec<TransactionReceipt>, SecretsError<M>> {
+        let mut head = self
+            .version_head(name)
+            .await?
+            .ok_or_else(|| SecretsError::NoVersions(name.to_string()))?;
+        let head_identifier = VersionedIdentifier::head(name);
+        let mut receipts = vec![
+            self.authorize_delegate(delegate, VersionedIdentifier::new(name, head.latest).to_bytes()).await?,
+            self.authorize_delegate(delegate, head_identifier.clone()).await?,
+        ];
+        if !head.delegates.contains(&delegate) {
+            head.delegates.push(delegate);
+            receipts.push(self.add_secret_paid(head_identifier, head.encode(), max_fee).await?);
+        }
+        Ok(receipts)
+    }
+
+    /// Revoke a delegate from every retained version and the head record,
+    /// and stop carrying it over on rotation.
+    pub async fn revoke_versioned_delegate(
+        &self,
+        name: &str,
+        delegate: Address,
+        max_fee: Option<U256>,
+    ) -> Result<Vec<TransactionReceipt>, SecretsError<M>> {
+        let mut head = self
+            .version_head(name)
+            .await?
+            .ok_or_else(|| SecretsError::NoVersions(name.to_string()))?;
+        let head_identifier = VersionedIdentifier::head(name);
+        let mut receipts = Vec::new();
+        if head.delegates.contains(&delegate) {
+            head.delegates.retain(|d| *d != delegate);
+            receipts.push(self.add_secret_paid(head_identifier.clone(), head.encode(), max_fee).await?);
+        }
+        for version in head.retained() {
+            let identifier = VersionedIdentifier::new(name, version);
+            receipts.push(self.revoke_delegate(delegate, identifier.to_bytes()).await?);
+        }
+        receipts.push(self.revoke_delegate(delegate, head_identifier).await?);
+        Ok(receipts)
+    }
+
+    /// Delete the versions a rotation scheduled for retirement, once its grace
+    /// period has elapsed.
+    pub async fn retire_versions(&self, rotation: &Rotation) -> Result<Vec<TransactionReceipt>, SecretsError<M>> {
+        if let Some(retire_after) = rotation.retire_after {
+            if let Ok(remaining) = retire_after.duration_since(SystemTime::now()) {
+                return Err(SecretsError::GracePeriodActive { name: rotation.name.clone(), remaining });
+            }
+        }
+        if rotation.pending_retirement.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.prune_versions(&rotation.name, rotation.version, rotation.max_fee).await
+    }
+
+    /// Delete every retained version older than `keep_from` and advance the
+    /// oldest version recorded in the head, refusing to pay more than
+    /// `max_fee` for the head update.
+    pub async fn prune_versions(
+        &self,
+        name: &str,
+        keep_from: u32,
+        max_fee: Option<U256>,
+    ) -> Result<Vec<TransactionReceipt>, SecretsError<M>> {
+        let head = self
+            .version_head(name)
+            .await?
+            .ok_or_else(|| SecretsError::NoVersions(name.to_string()))?;
+        if keep_from > head.latest {
+            return Err(SecretsError::VersionNotFound { name: name.to_string(), version: keep_from });
+        }
+
+        let mut receipts = Vec::new();
+        for version in head.oldest..keep_from {
+            let identifier = VersionedIdentifier::new(name, version);
+            receipts.push(self.delete_secret(identifier.to_bytes()).await?);
+        }
+        if keep_from > head.oldest {
+            let head = VersionHead { oldest: keep_from, ..head };
+            receipts.push(self.add_secret_paid(VersionedIdentifier::head(name), head.encode(), max_fee).await?);
+        }
+        Ok(receipts)
+    }
+}
@@ -1,19 +1,45 @@
 use std::io;
-use std::fs::{File};
-use std::io::{Read};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
 
 use ethers::types::H160;
 use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Convert an &str to an H160
 pub fn str_to_h160(address: &str) -> Result<H160, Box<dyn Error>> {
     address.parse().map_err(|e| Box::new(e) as Box<dyn Error>)
 }
 
-/// Save a public or secret key to file
+/// Seconds since the Unix epoch
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Write a file readable and writable only by its owner (0600 on Unix).
+/// Permissions are tightened on existing files too.
+pub fn write_private_file(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(data)?;
+    file.sync_all()
+}
+
+/// Save a public or secret key to file, readable only by its owner
 pub fn save_key_to_file(key_data: &str, file_path: &str) -> Result<(), std::io::Error> {
-    std::fs::write(file_path, key_data)?;
-    Ok(())
+    write_private_file(Path::new(file_path), key_data.as_bytes())
 }
 
 /// Reads a file and returns its contents as a hex string.
@@ -1,19 +1,17 @@
 // tests/common/mod.rs
+#![allow(dead_code)]
 
-use swtch_sdk::{SwtchSDK, BlockchainConfig, ChainType, context::{ContextManager, Config}, identity::IdentityManager, NetworkType, TestnetType, WalletConfig};
+use swtch_sdk::{SwtchSDK, BlockchainConfig, ChainType, context::{ContextManager, Config}, identity::IdentityManager, net::NetworkManager, reputation::ReputationManager, NetworkType, TestnetType, WalletConfig};
 use ethers::prelude::*;
-use ethers::providers::{Provider as EthersProvider, Http, JsonRpcClient};
+use ethers::providers::{HttpClientError, JsonRpcClient, JsonRpcError};
 use ethers::types::{
     transaction::eip2718::TypedTransaction,
     BlockId, TxHash, TransactionReceipt, U64, Bytes, Address,
 };
 use std::sync::Arc;
-use mockall::mock;
 use mockall::predicate::*;
 use rand::Rng;
 use async_trait::async_trait;
-use std::fmt;
-use std::marker::PhantomData;
 
 use std::fmt::Debug;
 use serde::Serialize;
@@ -58,6 +56,11 @@ impl Middleware for CustomMockProvider {
         Err(ProviderError::CustomError("Mock get_block_number".to_string()))
     }
 
+    async fn get_chainid(&self) -> Result<U256, Self::Error> {
+        Err(ProviderError::CustomError("Mock get_chainid".to_string()))
+    }
+
+    #[allow(mismatched_lifetime_syntaxes)]
     async fn send_transaction<T: Into<TypedTransaction> + Send + Sync>(
         &self,
         _tx: T,
@@ -81,9 +84,78 @@ impl Middleware for CustomMockProvider {
     // Implement other required methods...
 }
 
+// Scripted provider: a real ethers `Provider` over a client that answers each
+// JSON-RPC request with a test-supplied handler
+pub type RpcHandler = dyn Fn(&str, serde_json::Value) -> Result<serde_json::Value, String> + Send + Sync;
+
+#[derive(Clone)]
+pub struct ScriptedClient {
+    handler: Arc<RpcHandler>,
+}
+
+impl Debug for ScriptedClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ScriptedClient")
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for ScriptedClient {
+    type Error = ProviderError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(params)?;
+        let result = (self.handler)(method, params).map_err(|message| {
+            let response = JsonRpcError { code: -32000, message, data: None };
+            ProviderError::JsonRpcClientError(Box::new(HttpClientError::JsonRpcError(response)))
+        })?;
+        Ok(serde_json::from_value(result)?)
+    }
+}
+
+// Function to create a scripted provider. Handler errors become JSON-RPC error
+// responses, so an error mentioning "revert" reads as a contract revert.
+// Requests the handler doesn't know should return an error naming the method.
+pub fn scripted_provider<F>(handler: F) -> Provider<ScriptedClient>
+where
+    F: Fn(&str, serde_json::Value) -> Result<serde_json::Value, String> + Send + Sync + 'static,
+{
+    Provider::new(ScriptedClient { handler: Arc::new(handler) }).interval(std::time::Duration::from_millis(10))
+}
+
+// The 4-byte selector and arguments of an `eth_call` or `eth_sendTransaction`
+pub fn call_data(params: &serde_json::Value) -> Bytes {
+    let tx = &params[0];
+    let data = tx.get("input").or_else(|| tx.get("data")).cloned().unwrap_or_default();
+    serde_json::from_value(data).unwrap_or_default()
+}
+
+// Answer the requests a mined legacy transaction goes through: gas estimation,
+// sending, and polling for the transaction and its receipt
+pub fn answer_transaction(method: &str) -> Option<serde_json::Value> {
+    let hash = H256::repeat_byte(0x11);
+    let value = match method {
+        "eth_chainId" => serde_json::json!("0x1"),
+        "eth_gasPrice" => serde_json::json!("0x3b9aca00"),
+        "eth_estimateGas" => serde_json::json!("0x5208"),
+        "eth_blockNumber" => serde_json::json!("0x1"),
+        "eth_sendTransaction" => serde_json::json!(hash),
+        "eth_getTransactionByHash" => serde_json::json!(Transaction { hash, block_number: Some(U64::one()), ..Default::default() }),
+        "eth_getTransactionReceipt" => {
+            serde_json::json!(TransactionReceipt { transaction_hash: hash, ..mock_transaction_receipt() })
+        }
+        _ => return None,
+    };
+    Some(value)
+}
+
 // Setup function for SwtchSDK
 pub fn setup_sdk() -> SwtchSDK {
-    let mut sdk = SwtchSDK::new();
+    let sdk = SwtchSDK::new();
     let mut context_manager = ContextManager::new();
     
     // Add a test configuration
@@ -124,6 +196,13 @@ pub fn random_address() -> Address {
     Address::from(bytes)
 }
 
+// Function to create a unique, empty scratch directory
+pub fn temp_dir(prefix: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("swtch-{}-{:x}", prefix, rand::thread_rng().gen::<u64>()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
 // Function to create a mock IdentityManager
 pub fn mock_identity_manager() -> IdentityManager<CustomMockProvider> {
     let mock_provider = mock_provider();
@@ -132,6 +211,17 @@ pub fn mock_identity_manager() -> IdentityManager<CustomMockProvider> {
     IdentityManager::new(contract_address, Arc::new(mock_provider), wallet)
 }
 
+// Function to create a mock NetworkManager
+pub fn mock_network_manager() -> NetworkManager<CustomMockProvider> {
+    NetworkManager::new(Address::random(), Arc::new(mock_provider()))
+}
+
+// Function to create a mock ReputationManager
+pub fn mock_reputation_manager() -> ReputationManager<CustomMockProvider> {
+    let wallet = LocalWallet::new(&mut rand::thread_rng());
+    ReputationManager::new(Address::random(), Arc::new(mock_provider()), wallet)
+}
+
 // Helper function to create a mock transaction receipt
 pub fn mock_transaction_receipt() -> TransactionReceipt {
     TransactionReceipt {
@@ -1,12 +1,9 @@
 // tests/did_tests.rs
 
-use swtch_sdk::identity::{IdentityManager, Identity};
 use ethers::prelude::*;
-use std::sync::Arc;
-use async_trait::async_trait;
 
 mod common;
-use common::{CustomMockProvider, mock_identity_manager, random_address};
+use common::{mock_identity_manager, random_address};
 
 #[tokio::test]
 async fn test_create_identity_manager() {
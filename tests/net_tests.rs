@@ -0,0 +1,454 @@
+// tests/net_tests.rs
+
+use ethers::prelude::*;
+use std::sync::Arc;
+use std::time::Duration;
+use swtch_sdk::net::{
+    descriptor_hash, rank_candidates, ChannelSink, ProviderAgent, Capacity, DescriptorError, DiscoveryClient, Endpoint, Health, HealthChecker, NetworkError,
+    NetworkService, Pricing, PricingUnit, Protocol, ProviderPage, ProviderSelector, Registration, ScoredProvider, SelectionPolicy, ServiceDescriptor,
+    ServiceFilter, ServiceType, SignedServiceDescriptor,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+mod common;
+use common::{answer_transaction, call_data, create_test_wallet, mock_network_manager, mock_reputation_manager, random_address, scripted_provider};
+
+fn sample_descriptor(provider: Address) -> ServiceDescriptor {
+    let pricing = Pricing { amount: U256::from(1_000_000u64), unit: PricingUnit::PerGigabyte, token: None };
+    ServiceDescriptor::new(provider, ServiceType::Vpn, pricing, "eu-west")
+        .with_endpoint(Endpoint::new(Protocol::Wireguard, "vpn.example.org", 51820))
+        .with_endpoint(Endpoint::new(Protocol::Https, "vpn.example.org", 443))
+        .with_capacity(Capacity { max_clients: 250, bandwidth_mbps: Some(1000) })
+        .with_version("1.4.0")
+}
+
+#[test]
+fn test_descriptor_signs_and_roundtrips_through_details() {
+    let wallet = create_test_wallet();
+    let signed = sample_descriptor(wallet.address()).sign(&wallet).unwrap();
+    let details = signed.to_details().unwrap();
+
+    let parsed = SignedServiceDescriptor::from_details(&details).unwrap();
+    assert_eq!(parsed, signed);
+    parsed.verify_for(wallet.address()).unwrap();
+    assert_eq!(parsed.descriptor.protocols(), vec![Protocol::Wireguard, Protocol::Https]);
+    assert!(details.contains("\"service_type\":\"vpn\""));
+}
+
+#[test]
+fn test_canonical_json_sorts_keys() {
+    let descriptor = sample_descriptor(random_address());
+    let json = descriptor.canonical_json().unwrap();
+    assert!(json.starts_with("{\"capacity\":{\"bandwidth_mbps\":1000,\"max_clients\":250},\"endpoints\":"));
+    assert!(!json.contains(": ") && !json.contains(", "));
+    assert_eq!(json, descriptor.clone().canonical_json().unwrap());
+}
+
+#[test]
+fn test_descriptor_validation() {
+    let wallet = create_test_wallet();
+    let provider = wallet.address();
+
+    let no_endpoints = ServiceDescriptor { endpoints: vec![], ..sample_descriptor(provider) };
+    assert!(matches!(no_endpoints.sign(&wallet), Err(DescriptorError::Invalid(_))));
+
+    let mut bad_port = sample_descriptor(provider);
+    bad_port.endpoints[0].port = 0;
+    assert!(matches!(bad_port.sign(&wallet), Err(DescriptorError::Invalid(_))));
+
+    let no_version = sample_descriptor(provider).with_version(" ");
+    assert!(matches!(no_version.sign(&wallet), Err(DescriptorError::Invalid(_))));
+
+    let other_provider = sample_descriptor(random_address());
+    assert!(matches!(other_provider.sign(&wallet), Err(DescriptorError::ProviderMismatch { .. })));
+}
+
+#[test]
+fn test_descriptor_verification_rejects_tampering() {
+    let wallet = create_test_wallet();
+    let signed = sample_descriptor(wallet.address()).sign(&wallet).unwrap();
+
+    let mut repriced = signed.clone();
+    repriced.descriptor.pricing.amount = U256::one();
+    assert!(matches!(repriced.verify(), Err(DescriptorError::ProviderMismatch { .. })));
+
+    assert!(matches!(signed.verify_for(random_address()), Err(DescriptorError::ProviderMismatch { .. })));
+    assert!(matches!(
+        SignedServiceDescriptor::from_details("vpn at 1.2.3.4"),
+        Err(DescriptorError::Format(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_network_manager_validates_before_sending() {
+    let manager = mock_network_manager();
+    let wallet = create_test_wallet();
+    let signed = sample_descriptor(wallet.address()).sign(&wallet).unwrap();
+
+    // Stored under the wrong provider: rejected before any transaction is sent.
+    let result = manager.add_network_service(random_address(), &signed).await;
+    assert!(matches!(result, Err(NetworkError::Descriptor(DescriptorError::ProviderMismatch { .. }))));
+
+    let result = manager.update_network_service(wallet.address(), &signed).await;
+    assert!(matches!(result, Err(NetworkError::Contract(_))));
+    assert!(result.unwrap_err().to_string().contains("Mock send_transaction"));
+
+    let result = manager.get_network_service(wallet.address()).await;
+    assert!(matches!(result, Err(NetworkError::Contract(_))));
+}
+
+#[test]
+fn test_provider_page_offsets() {
+    let page = |offset: u64, len: usize, total: u64| ProviderPage {
+        providers: (0..len).map(|_| random_address()).collect(),
+        offset,
+        total,
+    };
+    assert_eq!(page(0, 2, 5).next_offset(), Some(2));
+    assert_eq!(page(2, 2, 5).next_offset(), Some(4));
+    assert_eq!(page(4, 1, 5).next_offset(), None);
+    // An empty page ends iteration even if the registry shrank mid-read.
+    assert_eq!(page(4, 0, 9).next_offset(), None);
+}
+
+#[tokio::test]
+async fn test_batched_lookup_and_paging_surface_registry_errors() {
+    let manager = mock_network_manager();
+    assert!(manager.get_network_services(&[]).await.unwrap().is_empty());
+
+    // Resolving the chain's Multicall3 deployment needs an RPC round trip.
+    let result = manager.get_network_services(&[random_address(), random_address()]).await;
+    assert!(matches!(result, Err(NetworkError::Contract(_))));
+    assert!(result.unwrap_err().to_string().contains("Mock get_chainid"));
+
+    let manager = mock_network_manager().with_multicall_address(random_address());
+    let result = manager.get_network_services(&[random_address()]).await;
+    assert!(matches!(result, Err(NetworkError::Contract(_))));
+    assert!(result.unwrap_err().to_string().contains("Mock call"));
+
+    let result = manager.get_service_providers_page(0, 10).await;
+    assert!(result.unwrap_err().to_string().contains("Mock call"));
+}
+
+#[tokio::test]
+async fn test_provider_pages_are_sliced_from_the_full_list() {
+    let registered: Vec<Address> = (0..5).map(|_| random_address()).collect();
+    let providers = registered.clone();
+    let provider = scripted_provider(move |method, _| match method {
+        "eth_call" => {
+            let list = ethers::abi::Token::Array(providers.iter().map(|a| ethers::abi::Token::Address(*a)).collect());
+            Ok(serde_json::json!(Bytes::from(ethers::abi::encode(&[list]))))
+        }
+        _ => Err(format!("unexpected {}", method)),
+    });
+    let manager = swtch_sdk::net::NetworkManager::new(random_address(), Arc::new(provider));
+
+    let page = manager.get_service_providers_page(2, 2).await.unwrap();
+    assert_eq!(page, ProviderPage { providers: registered[2..4].to_vec(), offset: 2, total: 5 });
+    assert_eq!(page.next_offset(), Some(4));
+    let last = manager.get_service_providers_page(4, 2).await.unwrap();
+    assert_eq!(last.providers, registered[4..].to_vec());
+    assert_eq!(last.next_offset(), None);
+    assert!(manager.get_service_providers_page(9, 2).await.unwrap().providers.is_empty());
+}
+
+fn network_service(descriptor: ServiceDescriptor, is_active: bool) -> NetworkService {
+    let wallet = create_test_wallet();
+    let descriptor = ServiceDescriptor { provider: wallet.address(), ..descriptor }.sign(&wallet).unwrap();
+    NetworkService {
+        owner: wallet.address(),
+        service_details: descriptor.to_details().unwrap(),
+        descriptor: Some(descriptor),
+        is_active,
+    }
+}
+
+/// A local stand-in for a provider's HTTP endpoint that answers every request
+/// with `status`.
+async fn http_stand_in(status: &'static str) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut request = [0u8; 512];
+            let _ = socket.read(&mut request).await;
+            let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+    port
+}
+
+async fn closed_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+#[tokio::test]
+async fn test_health_checker_probes_endpoints() {
+    let checker = HealthChecker::new().with_timeout(Duration::from_secs(2));
+    let healthy = http_stand_in("200 OK").await;
+    let failing = http_stand_in("503 Service Unavailable").await;
+    let closed = closed_port().await;
+
+    let probe = checker.probe(&Endpoint::new(Protocol::Http, "127.0.0.1", healthy)).await;
+    assert!(matches!(probe.health, Health::Healthy { .. }));
+    let probe = checker.probe(&Endpoint::new(Protocol::Tcp, "127.0.0.1", failing)).await;
+    assert!(matches!(probe.health, Health::Healthy { .. }));
+    let probe = checker.probe(&Endpoint::new(Protocol::Http, "127.0.0.1", failing)).await;
+    assert_eq!(probe.health, Health::Unhealthy("HTTP status 503".to_string()));
+    let probe = checker.probe(&Endpoint::new(Protocol::Tcp, "127.0.0.1", closed)).await;
+    assert!(matches!(probe.health, Health::Unhealthy(_)));
+    let probe = checker.probe(&Endpoint::new(Protocol::Wireguard, "127.0.0.1", closed)).await;
+    assert_eq!(probe.health, Health::Unchecked);
+}
+
+#[tokio::test]
+async fn test_health_checker_caps_concurrent_probes() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let (counter, max) = (in_flight.clone(), peak.clone());
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let (counter, max) = (counter.clone(), max.clone());
+            tokio::spawn(async move {
+                max.fetch_max(counter.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                let mut request = [0u8; 512];
+                let _ = socket.read(&mut request).await;
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                counter.fetch_sub(1, Ordering::SeqCst);
+                let _ = socket.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await;
+            });
+        }
+    });
+
+    let endpoint = Endpoint::new(Protocol::Http, "127.0.0.1", port);
+    let descriptor = ServiceDescriptor { endpoints: vec![endpoint.clone(), endpoint.clone(), endpoint], ..sample_descriptor(random_address()) };
+    let service = network_service(descriptor, true);
+    let checker = HealthChecker::new().with_timeout(Duration::from_secs(2)).with_max_concurrent_probes(1);
+    let candidate = checker.check(service.owner, service).await;
+    assert_eq!(candidate.probes.len(), 3);
+    assert!(candidate.probes.iter().all(|p| matches!(p.health, Health::Healthy { .. })));
+    assert_eq!(peak.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_candidates_are_filtered_and_ranked() {
+    let checker = HealthChecker::new().with_timeout(Duration::from_secs(2));
+    let healthy = http_stand_in("204 No Content").await;
+    let closed = closed_port().await;
+
+    let base = sample_descriptor(random_address());
+    let up = ServiceDescriptor { endpoints: vec![Endpoint::new(Protocol::Http, "127.0.0.1", healthy)], ..base.clone() };
+    let down = ServiceDescriptor { endpoints: vec![Endpoint::new(Protocol::Tcp, "127.0.0.1", closed)], ..base.clone() };
+    let cheap_down = ServiceDescriptor {
+        pricing: Pricing { amount: U256::one(), ..base.pricing.clone() },
+        ..down.clone()
+    };
+
+    let mut candidates = Vec::new();
+    for descriptor in [cheap_down, down, up] {
+        let service = network_service(descriptor, true);
+        candidates.push(checker.check(service.owner, service).await);
+    }
+    rank_candidates(&mut candidates);
+    assert!(candidates[0].is_healthy());
+    assert!(candidates[0].best_latency().is_some());
+    assert!(!candidates[1].is_healthy());
+    assert_eq!(candidates[1].service.descriptor.as_ref().unwrap().descriptor.pricing.amount, U256::one());
+
+    let storage = network_service(ServiceDescriptor { service_type: ServiceType::Storage, ..base.clone() }, true);
+    let inactive = network_service(base.clone(), false);
+    let vpn_eu = ServiceFilter::new().with_service_type(ServiceType::Vpn).with_region("EU-WEST");
+    assert!(vpn_eu.matches(&candidates[0].service));
+    assert!(!vpn_eu.matches(&storage));
+    assert!(!vpn_eu.matches(&inactive));
+    assert!(vpn_eu.clone().include_inactive().matches(&inactive));
+    assert!(!ServiceFilter::new().with_region("us-east").matches(&candidates[0].service));
+    assert!(!ServiceFilter::new().with_protocol(Protocol::Udp).matches(&candidates[0].service));
+}
+
+#[tokio::test]
+async fn test_legacy_services_only_match_filters_without_descriptor_criteria() {
+    let legacy = NetworkService {
+        owner: random_address(),
+        service_details: "vpn;eu-west;wg://10.0.0.1:51820".to_string(),
+        descriptor: None,
+        is_active: true,
+    };
+    assert!(legacy.is_legacy());
+    assert!(ServiceFilter::new().matches(&legacy));
+    assert!(!ServiceFilter::new().with_region("eu-west").matches(&legacy));
+    assert!(!ServiceFilter::new().with_service_type(ServiceType::Vpn).matches(&legacy));
+    let inactive = NetworkService { is_active: false, ..legacy.clone() };
+    assert!(!ServiceFilter::new().matches(&inactive));
+
+    let candidate = HealthChecker::new().check(legacy.owner, legacy).await;
+    assert!(candidate.probes.is_empty());
+    assert!(!candidate.is_healthy());
+}
+
+#[tokio::test]
+async fn test_discovery_surfaces_registry_errors() {
+    let client = DiscoveryClient::new(Arc::new(mock_network_manager())).with_refresh_interval(Duration::from_secs(60));
+    let result = client.discover(&ServiceFilter::new()).await;
+    assert!(matches!(result, Err(NetworkError::Contract(_))));
+    assert!(result.unwrap_err().to_string().contains("Mock call"));
+}
+
+fn scored(score: u64) -> ScoredProvider {
+    let service = network_service(sample_descriptor(random_address()), true);
+    ScoredProvider {
+        provider: service.owner,
+        service,
+        producer_score: U256::from(score),
+        product_score: None,
+        score: U256::from(score),
+    }
+}
+
+#[test]
+fn test_selection_policies() {
+    use ethers::core::rand::{rngs::StdRng, SeedableRng};
+    let mut rng = StdRng::seed_from_u64(7);
+    let providers = vec![scored(10), scored(50), scored(0), scored(40)];
+
+    let top = SelectionPolicy::TopK(2).apply(providers.clone(), &mut rng);
+    assert_eq!(top.iter().map(|s| s.provider.score.as_u64()).collect::<Vec<_>>(), vec![50, 40]);
+    assert_eq!((top[1].reason.rank, top[1].reason.eligible), (2, 4));
+    assert!(top[0].reason.to_string().contains("within the top 2"));
+
+    let above = SelectionPolicy::MinThreshold(U256::from(10)).apply(providers.clone(), &mut rng);
+    assert_eq!(above.len(), 3);
+    assert_eq!(above[2].reason.eligible, 3);
+
+    let mut picks = [0usize; 4];
+    for _ in 0..500 {
+        let pick = SelectionPolicy::WeightedRandom.apply(providers.clone(), &mut rng).remove(0);
+        let probability = pick.reason.probability.unwrap();
+        assert!((probability - pick.provider.score.as_u64() as f64 / 100.0).abs() < 1e-9);
+        picks[pick.reason.rank - 1] += 1;
+    }
+    // Ranks are 50, 40, 10, 0: the zero-score provider is never drawn.
+    assert!(picks[0] > picks[1] && picks[1] > picks[2]);
+    assert_eq!(picks[3], 0);
+
+    let zeros = vec![scored(0), scored(0)];
+    let pick = SelectionPolicy::WeightedRandom.apply(zeros, &mut rng);
+    assert_eq!(pick[0].reason.probability, Some(0.5));
+    assert!(SelectionPolicy::WeightedRandom.apply(Vec::new(), &mut rng).is_empty());
+}
+
+#[tokio::test]
+async fn test_provider_selector_surfaces_registry_errors() {
+    let selector = ProviderSelector::new(Arc::new(mock_network_manager()), Arc::new(mock_reputation_manager()))
+        .with_product([1u8; 32], 2);
+    let result = selector.select(&ServiceFilter::new(), SelectionPolicy::TopK(3)).await;
+    assert!(matches!(result, Err(NetworkError::Contract(_))));
+}
+
+#[tokio::test]
+async fn test_agent_publishes_signed_heartbeats() {
+    let wallet = create_test_wallet();
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(4);
+    let mut agent = ProviderAgent::new(
+        Arc::new(mock_network_manager()),
+        wallet.clone(),
+        sample_descriptor(wallet.address()),
+        Arc::new(ChannelSink::new(sender)),
+    );
+
+    let first = agent.heartbeat(Some(12)).await.unwrap();
+    let second = agent.heartbeat(None).await.unwrap();
+    assert_eq!(receiver.recv().await.unwrap(), first);
+    assert_eq!(receiver.recv().await.unwrap(), second);
+    assert_eq!((first.heartbeat.sequence, second.heartbeat.sequence), (1, 2));
+    assert_eq!(first.heartbeat.descriptor_hash, second.heartbeat.descriptor_hash);
+    first.verify().unwrap();
+
+    let mut forged = second.clone();
+    forged.heartbeat.load = Some(0);
+    assert!(matches!(forged.verify(), Err(DescriptorError::ProviderMismatch { .. })));
+
+    drop(receiver);
+    assert!(agent.heartbeat(None).await.unwrap_err().contains("closed"));
+}
+
+#[tokio::test]
+async fn test_agent_heartbeat_hashes_the_registered_descriptor() {
+    let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = sent.clone();
+    let provider = scripted_provider(move |method, params| {
+        if method == "eth_call" {
+            // isServiceProvider: not yet registered
+            return Ok(serde_json::json!(Bytes::from(ethers::abi::encode(&[ethers::abi::Token::Bool(false)]))));
+        }
+        if method == "eth_sendTransaction" {
+            recorded.lock().unwrap().push(call_data(&params));
+        }
+        answer_transaction(method).ok_or_else(|| format!("unexpected {}", method))
+    });
+    let wallet = create_test_wallet();
+    let (sender, _receiver) = tokio::sync::mpsc::channel(4);
+    let mut agent = ProviderAgent::new(
+        Arc::new(swtch_sdk::net::NetworkManager::new(random_address(), Arc::new(provider))),
+        wallet.clone(),
+        ServiceDescriptor { updated_at: 0, ..sample_descriptor(wallet.address()) },
+        Arc::new(ChannelSink::new(sender)),
+    );
+
+    assert!(matches!(agent.ensure_registered().await.unwrap(), Registration::Registered(_)));
+    let calldata = sent.lock().unwrap().pop().unwrap();
+    let tokens = ethers::abi::decode(&[ethers::abi::ParamType::Address, ethers::abi::ParamType::String], &calldata[4..]).unwrap();
+    let details = tokens[1].clone().into_string().unwrap();
+    let registered = SignedServiceDescriptor::from_details(&details).unwrap().descriptor;
+    assert_ne!(registered.updated_at, 0);
+    assert_eq!(agent.descriptor(), &registered);
+
+    let heartbeat = agent.heartbeat(None).await.unwrap();
+    assert_eq!(heartbeat.heartbeat.descriptor_hash, descriptor_hash(&registered).unwrap());
+}
+
+#[tokio::test]
+async fn test_dropped_registry_transactions_are_errors() {
+    let provider = scripted_provider(|method, _| match method {
+        "eth_call" => Ok(serde_json::json!(Bytes::from(ethers::abi::encode(&[ethers::abi::Token::Bool(false)])))),
+        // The node forgets the transaction: it was dropped, not mined.
+        "eth_getTransactionByHash" => Ok(serde_json::Value::Null),
+        _ => answer_transaction(method).ok_or_else(|| format!("unexpected {}", method)),
+    });
+    let wallet = create_test_wallet();
+    let (sender, _receiver) = tokio::sync::mpsc::channel(4);
+    let mut agent = ProviderAgent::new(
+        Arc::new(swtch_sdk::net::NetworkManager::new(random_address(), Arc::new(provider))),
+        wallet.clone(),
+        sample_descriptor(wallet.address()),
+        Arc::new(ChannelSink::new(sender)),
+    );
+
+    let dropped = H256::repeat_byte(0x11);
+    assert!(matches!(agent.ensure_registered().await, Err(NetworkError::Dropped(hash)) if hash == dropped));
+    assert!(matches!(agent.deregister().await, Err(NetworkError::Dropped(hash)) if hash == dropped));
+}
+
+#[tokio::test]
+async fn test_agent_run_fails_when_registration_fails() {
+    let wallet = create_test_wallet();
+    let (sender, _receiver) = tokio::sync::mpsc::channel(4);
+    let mut agent = ProviderAgent::new(
+        Arc::new(mock_network_manager()),
+        wallet.clone(),
+        sample_descriptor(wallet.address()),
+        Arc::new(ChannelSink::new(sender)),
+    )
+    .with_heartbeat_interval(Duration::from_millis(10));
+
+    assert!(matches!(agent.ensure_registered().await, Err(NetworkError::Contract(_))));
+    let result = agent.run(std::future::ready(())).await;
+    assert!(result.unwrap_err().to_string().contains("Mock call"));
+}
@@ -0,0 +1,682 @@
+// tests/reputation_tests.rs
+
+use ethers::prelude::*;
+use ethers::utils::keccak256;
+use std::sync::Arc;
+use std::time::Duration;
+use swtch_sdk::reputation::{
+    decode_escrow_call, decode_score_change, permit_digest, permit_typehash, sign_permit, ActionRegistry, ActionStats,
+    ActionType, ActionWeights, Approval, ApprovalStrategy, DecayModel, Escrow, EscrowAsset, EscrowCall, EscrowError, EscrowKind,
+    EscrowLedger, EscrowNotification, EscrowState, EscrowWatcher, FixedPoint, KnownAction, MAX_DECIMALS, NftApprovalScope, Reputation,
+    ReputationError, ReputationHistory, ReputationIndexer, ReputationManager, Role, ScoreChange, ScoreEvent, ScoreSimulator, ScoringRules,
+    DEFAULT_ESCROW_TIMEOUT, DEFAULT_PERMIT_VALIDITY, SCORE_DECIMALS,
+};
+
+mod common;
+use common::{
+    answer_transaction, call_data, create_test_wallet, mock_provider, mock_reputation_manager, random_address, scripted_provider, temp_dir,
+    ScriptedClient,
+};
+
+fn wad(whole: u64) -> U256 {
+    U256::from(whole) * U256::exp10(SCORE_DECIMALS as usize)
+}
+
+#[test]
+fn test_fixed_point_formatting() {
+    assert_eq!(FixedPoint::score(wad(42)).to_string(), "42");
+    assert_eq!(FixedPoint::score(wad(12) + wad(1) / 2).to_string(), "12.5");
+    assert_eq!(FixedPoint::score(U256::one()).to_string(), "0.000000000000000001");
+    assert_eq!(FixedPoint::new(U256::from(1234), 2).to_string(), "12.34");
+    assert_eq!(FixedPoint::new(U256::from(7), 0).to_string(), "7");
+    assert_eq!(FixedPoint::score(wad(3) + wad(1) / 4).whole(), U256::from(3));
+    assert!((FixedPoint::score(wad(3) + wad(1) / 4).to_f64() - 3.25).abs() < 1e-12);
+    assert!(FixedPoint::score(U256::MAX).to_f64() > 1e59);
+    assert_eq!(FixedPoint::new(U256::one(), 200).decimals, MAX_DECIMALS);
+    assert_eq!(FixedPoint::new(U256::MAX, 200).whole(), U256::one());
+    let oversized = FixedPoint { raw: U256::from(5), decimals: u32::MAX };
+    assert!(oversized.whole().is_zero());
+    assert_eq!(oversized.to_string(), FixedPoint::new(U256::from(5), MAX_DECIMALS).to_string());
+}
+
+#[test]
+fn test_reputation_normalizes_fields() {
+    let did = random_address();
+    let reputation = Reputation::new(did, wad(5), wad(1) / 10, U256::exp10(17) * 15);
+    assert_eq!(reputation.consumer().to_string(), "5");
+    assert_eq!(reputation.producer().to_string(), "0.1");
+    assert_eq!(reputation.escrow().to_string(), "1.5");
+
+    let json = serde_json::to_string(&reputation).unwrap();
+    assert_eq!(serde_json::from_str::<Reputation>(&json).unwrap(), reputation);
+}
+
+#[tokio::test]
+async fn test_reputation_queries_surface_contract_errors() {
+    let manager = mock_reputation_manager();
+    let result = manager.get_reputation(random_address()).await;
+    assert!(result.unwrap_err().to_string().contains("Mock call"));
+
+    assert!(manager.get_reputations(&[]).await.unwrap().is_empty());
+    let result = manager.get_reputations(&[random_address()]).await;
+    assert!(result.unwrap_err().to_string().contains("Mock get_chainid"));
+
+    let manager = mock_reputation_manager().with_multicall_address(random_address());
+    let result = manager.get_reputations(&[random_address(), random_address()]).await;
+    assert!(result.unwrap_err().to_string().contains("Mock call"));
+}
+
+#[test]
+fn test_action_types_derive_from_names() {
+    let action = ActionType::from_name("SERVICE_DELIVERY");
+    assert_eq!(action.id(), keccak256(b"SERVICE_DELIVERY"));
+    assert_eq!(action, KnownAction::ServiceDelivery.action_type());
+    assert_eq!(<[u8; 32]>::from(KnownAction::Dispute), keccak256(b"DISPUTE"));
+    assert_ne!(ActionType::from_name("service_delivery").id(), action.id());
+
+    let registry = ActionRegistry::new().with_action("BANDWIDTH_SHARED");
+    assert_eq!(registry.actions().len(), KnownAction::ALL.len() + 1);
+    for known in KnownAction::ALL {
+        assert_eq!(registry.lookup(&known.action_type().id()).unwrap().name(), known.name());
+    }
+    assert_eq!(registry.get("BANDWIDTH_SHARED").unwrap().id(), keccak256(b"BANDWIDTH_SHARED"));
+
+    let unknown = [7u8; 32];
+    assert!(registry.lookup(&unknown).is_none());
+    assert_eq!(registry.describe(&unknown), format!("0x{}", hex::encode(unknown)));
+    assert_eq!(registry.describe(&keccak256(b"UPTIME")), "UPTIME");
+    assert!(ActionRegistry::empty().get("UPTIME").is_none());
+}
+
+#[test]
+fn test_action_weights_load_and_resolve() {
+    let weights = ActionWeights::from_json(r#"{"UPTIME": 3, "DISPUTE": "250000000000000000000"}"#).unwrap();
+    assert_eq!(weights.get("UPTIME"), Some(U256::from(3)));
+    assert_eq!(weights.get("DISPUTE"), Some(U256::from_dec_str("250000000000000000000").unwrap()));
+    assert!(ActionWeights::from_json(r#"{"UPTIME": "ten"}"#).is_err());
+
+    let resolved = weights.resolve(&ActionRegistry::new()).unwrap();
+    let names: Vec<&str> = resolved.iter().map(|(action, _)| action.name()).collect();
+    assert_eq!(names, ["DISPUTE", "UPTIME"]);
+
+    let typo = weights.clone().with_weight("UPTIEM", 1u64);
+    assert_eq!(typo.resolve(&ActionRegistry::new()).unwrap_err(), vec!["UPTIEM".to_string()]);
+
+    let path = temp_dir("weights").join("weights.json");
+    weights.save(&path).unwrap();
+    assert_eq!(ActionWeights::load(&path).unwrap(), weights);
+    let reweighted = weights.clone().with_weight("DISPUTE", 9u64);
+    reweighted.save(&path).unwrap();
+    assert_eq!(ActionWeights::load(&path).unwrap(), reweighted);
+    assert!(!path.with_extension("tmp").exists());
+}
+
+#[tokio::test]
+async fn test_apply_action_weights_checks_names_first() {
+    let manager = mock_reputation_manager();
+    let did = random_address();
+
+    let weights = ActionWeights::new().with_weight("UPTIME", 2u64).with_weight("NOT_AN_ACTION", 1u64);
+    let result = manager.apply_action_weights(did, &weights, &ActionRegistry::new()).await;
+    assert!(matches!(result, Err(ReputationError::UnknownActions(names)) if names == ["NOT_AN_ACTION"]));
+
+    let weights = ActionWeights::new().with_weight("UPTIME", 2u64);
+    let result = manager.apply_action_weights(did, &weights, &ActionRegistry::new()).await;
+    assert!(matches!(result, Err(ReputationError::Contract(_))));
+
+    let result = manager.update_score(did, true, KnownAction::ServiceDelivery, true).await;
+    assert!(result.unwrap_err().to_string().contains("Mock send_transaction"));
+}
+
+fn score_event(did: Address, timestamp: u64, role: Role, action: KnownAction, success: bool) -> ScoreEvent {
+    ScoreEvent {
+        did,
+        block_number: timestamp / 12,
+        transaction_index: 0,
+        transaction_hash: H256::random(),
+        timestamp,
+        change: ScoreChange::Action { role, action_type: action.into(), success },
+    }
+}
+
+#[test]
+fn test_history_analytics() {
+    let did = random_address();
+    let mut history = ReputationHistory::new();
+    history.record(score_event(did, 100, Role::Producer, KnownAction::ServiceDelivery, true));
+    history.record(score_event(did, 150, Role::Producer, KnownAction::ServiceDelivery, false));
+    history.record(score_event(did, 390, Role::Consumer, KnownAction::ServicePayment, true));
+    history.record(score_event(did, 400, Role::Producer, KnownAction::Uptime, true));
+    history.record(ScoreEvent {
+        change: ScoreChange::ProductScore { product_hash: [9u8; 32], score: U256::from(77) },
+        ..score_event(did, 420, Role::Producer, KnownAction::Uptime, true)
+    });
+    history.record(score_event(random_address(), 500, Role::Producer, KnownAction::Uptime, false));
+
+    assert_eq!(history.dids().count(), 2);
+    assert_eq!(history.events(did).len(), 5);
+    assert_eq!(history.events_between(did, 150, 400).len(), 2);
+    assert_eq!(history.last_block(), Some(500 / 12));
+
+    let series = history.time_series(did, None, Duration::from_secs(100));
+    let starts: Vec<u64> = series.iter().map(|b| b.start).collect();
+    assert_eq!(starts, [100, 200, 300, 400]);
+    assert_eq!(series[0].stats, ActionStats { successes: 1, failures: 1 });
+    assert_eq!(series[1].stats.total(), 0);
+    assert_eq!(history.time_series(did, Some(Role::Consumer), Duration::from_secs(100)).len(), 1);
+
+    let breakdown = history.breakdown(did, Some(Role::Producer));
+    assert_eq!(breakdown.len(), 2);
+    assert_eq!(breakdown[&KnownAction::ServiceDelivery.action_type().id()], ActionStats { successes: 1, failures: 1 });
+    assert_eq!(history.success_rate(did, None), Some(0.75));
+    assert_eq!(history.success_rate(did, Some(Role::Producer)), Some(2.0 / 3.0));
+    assert_eq!(history.success_rate(random_address(), None), None);
+    assert_eq!(history.product_score_history(did, [9u8; 32]), vec![(420, U256::from(77))]);
+
+    // At 500 the uptime success is one half-life old and keeps half its
+    // weight; the delivery success and failure are 4 and 3.5 half-lives old.
+    let model = DecayModel::new(Duration::from_secs(100)).with_weight(KnownAction::Uptime, 4.0);
+    let score = history.decayed_score(did, Some(Role::Producer), &model, 500);
+    assert!((score - (4.0 * 0.5 + 0.5f64.powi(4) - 0.5f64.powf(3.5))).abs() < 1e-9);
+    assert_eq!(history.decayed_score(did, None, &model, 50), 0.0);
+}
+
+#[test]
+fn test_decode_score_change_from_calldata() {
+    let manager = mock_reputation_manager();
+    let did = random_address();
+    let calldata = manager.contract.update_score(did, true, KnownAction::Uptime.into(), false).calldata().unwrap();
+    assert_eq!(
+        decode_score_change(&calldata),
+        Some((did, ScoreChange::Action { role: Role::Producer, action_type: KnownAction::Uptime.into(), success: false }))
+    );
+
+    let calldata = manager.contract.update_product_score(did, [3u8; 32], U256::from(5)).calldata().unwrap();
+    assert_eq!(
+        decode_score_change(&calldata),
+        Some((did, ScoreChange::ProductScore { product_hash: [3u8; 32], score: U256::from(5) }))
+    );
+
+    let calldata = manager.contract.set_action_weight(did, KnownAction::Dispute.into(), U256::from(8)).calldata().unwrap();
+    assert_eq!(
+        decode_score_change(&calldata),
+        Some((did, ScoreChange::ActionWeight { action_type: KnownAction::Dispute.into(), weight: U256::from(8) }))
+    );
+
+    let calldata = manager.contract.release_escrow().calldata().unwrap();
+    assert_eq!(decode_score_change(&calldata), None);
+    assert_eq!(decode_score_change(&[1, 2, 3]), None);
+}
+
+#[tokio::test]
+async fn test_reputation_indexer_surfaces_provider_errors() {
+    let mut indexer = ReputationIndexer::new(Arc::new(mock_provider()), random_address(), 0);
+    let result = indexer.sync().await;
+    assert!(result.unwrap_err().to_string().contains("Mock get_block_number"));
+    assert_eq!(indexer.history().last_block(), None);
+}
+
+#[tokio::test]
+async fn test_reputation_indexer_retries_blocks_and_receipts_the_node_lacks() {
+    let contract = random_address();
+    let did = random_address();
+    let input = mock_reputation_manager().contract.update_score(did, false, KnownAction::Uptime.into(), true).calldata().unwrap();
+    let tx = Transaction { hash: H256::repeat_byte(0x22), to: Some(contract), transaction_index: Some(U64::zero()), input, ..Default::default() };
+    let misses = Arc::new(std::sync::Mutex::new((1, 1)));
+    let remaining = misses.clone();
+    let provider = scripted_provider(move |method, params| match method {
+        "eth_blockNumber" => Ok(serde_json::json!(U64::from(1))),
+        "eth_getBlockByNumber" => {
+            let number: U64 = serde_json::from_value(params[0].clone()).unwrap();
+            // A lagging node doesn't have block 1 on the first request.
+            if number.as_u64() == 1 && std::mem::take(&mut remaining.lock().unwrap().0) > 0 {
+                return Ok(serde_json::Value::Null);
+            }
+            let transactions = if number.as_u64() == 1 { vec![tx.clone()] } else { vec![] };
+            Ok(serde_json::json!(Block::<Transaction> { number: Some(number), timestamp: U256::from(100), transactions, ..Default::default() }))
+        }
+        "eth_getTransactionReceipt" => {
+            if std::mem::take(&mut remaining.lock().unwrap().1) > 0 {
+                return Ok(serde_json::Value::Null);
+            }
+            Ok(answer_transaction(method).unwrap())
+        }
+        _ => Err(format!("unexpected {}", method)),
+    });
+    let mut indexer = ReputationIndexer::new(Arc::new(provider), contract, 0);
+
+    // Block 0 is indexed; the missing block 1 holds the cursor back.
+    assert!(indexer.sync().await.unwrap().is_empty());
+    assert_eq!(indexer.next_block(), 1);
+    assert!(!indexer.is_synced());
+
+    // The block arrives but its receipt doesn't yet.
+    assert!(indexer.sync().await.unwrap_err().to_string().contains("not available"));
+    assert_eq!(indexer.next_block(), 1);
+
+    let events = indexer.sync().await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].did, did);
+    assert_eq!(events[0].change, ScoreChange::Action { role: Role::Consumer, action_type: KnownAction::Uptime.into(), success: true });
+    assert!(indexer.is_synced());
+    assert_eq!(indexer.history().last_block(), Some(1));
+}
+
+#[test]
+fn test_simulator_predicts_score_updates() {
+    let did = random_address();
+    let mut simulator = ScoreSimulator::default();
+
+    let predicted = simulator.simulate(did, Role::Producer, KnownAction::ServiceDelivery, true);
+    assert_eq!((predicted.before, predicted.after), (U256::zero(), wad(1)));
+    assert_eq!(simulator.reputation(did).producer_score, U256::zero());
+
+    simulator.set_action_weight(did, KnownAction::ServiceDelivery, wad(5));
+    assert_eq!(simulator.update_score(did, Role::Producer, KnownAction::ServiceDelivery, true).after, wad(5));
+    assert_eq!(simulator.update_score(did, Role::Producer, KnownAction::Dispute, false).after, wad(4));
+    assert_eq!(simulator.update_score(did, Role::Consumer, KnownAction::ServiceDelivery, false).after, U256::zero());
+    assert_eq!(simulator.reputation(did), Reputation::new(did, U256::zero(), wad(4), U256::zero()));
+
+    let mut lenient = ScoreSimulator::new(ScoringRules::default().with_failure_penalty_bps(2_500));
+    lenient.seed(&Reputation::new(did, wad(10), wad(2), U256::one()));
+    assert_eq!(lenient.update_score(did, Role::Consumer, KnownAction::Dispute, false).after, wad(10) - wad(1) / 4);
+    assert_eq!(lenient.reputation(did).escrow_balance, U256::one());
+}
+
+#[test]
+fn test_simulator_replays_history_and_cross_checks() {
+    let did = random_address();
+    let mut history = ReputationHistory::new();
+    history.record(ScoreEvent {
+        change: ScoreChange::ActionWeight { action_type: KnownAction::Uptime.into(), weight: wad(3) },
+        ..score_event(did, 10, Role::Producer, KnownAction::Uptime, true)
+    });
+    history.record(score_event(did, 20, Role::Producer, KnownAction::Uptime, true));
+    history.record(score_event(did, 30, Role::Producer, KnownAction::Uptime, true));
+    history.record(score_event(did, 40, Role::Consumer, KnownAction::ServicePayment, true));
+    history.record(ScoreEvent {
+        change: ScoreChange::ProductScore { product_hash: [4u8; 32], score: U256::from(90) },
+        ..score_event(did, 50, Role::Producer, KnownAction::Uptime, true)
+    });
+
+    let mut simulator = ScoreSimulator::default();
+    let updates = simulator.replay_history(&history);
+    assert_eq!(updates.len(), 3);
+    assert_eq!(updates[1].before, wad(3));
+    assert_eq!(simulator.product_score(did, [4u8; 32]), U256::from(90));
+
+    let on_chain = Reputation::new(did, wad(1), wad(6), U256::from(123));
+    assert_eq!(simulator.compare(&on_chain), None);
+    let drifted = Reputation::new(did, wad(1), wad(7), U256::zero());
+    let mismatch = simulator.compare(&drifted).unwrap();
+    assert_eq!(mismatch.simulated.producer_score, wad(6));
+    assert_eq!(mismatch.on_chain, drifted);
+}
+
+#[tokio::test]
+async fn test_simulator_cross_check_surfaces_contract_errors() {
+    let simulator = ScoreSimulator::default();
+    let manager = mock_reputation_manager().with_multicall_address(random_address());
+    assert!(simulator.cross_check(&manager, &[]).await.unwrap().is_empty());
+    assert!(simulator.cross_check(&manager, &[random_address()]).await.is_err());
+}
+
+#[test]
+fn test_escrow_state_machine_guards_transitions() {
+    let mut escrow = Escrow::new(random_address(), EscrowAsset::Erc721 { token_id: Some(U256::from(42)) });
+    assert_eq!(escrow.kind(), EscrowKind::Erc721);
+    assert_eq!(escrow.state(), EscrowState::Initiated);
+    assert_eq!(
+        escrow.check(EscrowState::Released),
+        Err(EscrowError::InvalidTransition { from: EscrowState::Initiated, to: EscrowState::Released })
+    );
+
+    escrow.transition(EscrowState::Funded, Some(H256::random()), Some(7)).unwrap();
+    escrow.dispute().unwrap();
+    assert!(escrow.dispute().is_err());
+    escrow.transition(EscrowState::Refunded, Some(H256::random()), Some(9)).unwrap();
+    assert!(!escrow.is_active());
+    assert!(escrow.transition(EscrowState::Released, None, None).is_err());
+
+    let states: Vec<EscrowState> = escrow.transitions().iter().map(|t| t.state).collect();
+    assert_eq!(states, [EscrowState::Initiated, EscrowState::Funded, EscrowState::Disputed, EscrowState::Refunded]);
+}
+
+#[test]
+fn test_escrow_ledger_reconstructs_status_from_calldata() {
+    let manager = mock_reputation_manager();
+    let depositor = random_address();
+    let decode = |calldata: Bytes, value: u64| decode_escrow_call(&calldata, U256::from(value)).unwrap();
+
+    let open_eth = decode(manager.contract.initiate_escrow().calldata().unwrap(), 500);
+    assert_eq!(open_eth, EscrowCall::Initiate(EscrowAsset::Native { amount: U256::from(500) }));
+    let open_nft = decode(manager.contract.initiate_erc721_escrow().calldata().unwrap(), 0);
+    assert_eq!(open_nft, EscrowCall::Initiate(EscrowAsset::Erc721 { token_id: None }));
+    let release_eth = decode(manager.contract.release_escrow().calldata().unwrap(), 0);
+    let refund_erc20 = decode(manager.contract.refund_erc20_escrow().calldata().unwrap(), 0);
+    assert_eq!(refund_erc20, EscrowCall::Refund(EscrowKind::Erc20));
+    let weight = manager.contract.set_action_weight(depositor, [0u8; 32], U256::one()).calldata().unwrap();
+    assert_eq!(decode_escrow_call(&weight, U256::zero()), None);
+
+    let mut ledger = EscrowLedger::new();
+    // A refund for an escrow opened before indexing started is ignored.
+    ledger.apply(depositor, &refund_erc20, H256::random(), 1, 10);
+    assert_eq!(ledger.status(depositor, EscrowKind::Erc20), None);
+
+    ledger.apply(depositor, &open_eth, H256::random(), 2, 20);
+    ledger.apply(depositor, &open_nft, H256::random(), 3, 30);
+    assert_eq!(ledger.status(depositor, EscrowKind::Native), Some(EscrowState::Funded));
+    assert_eq!(ledger.active().count(), 2);
+
+    ledger.apply(depositor, &release_eth, H256::random(), 4, 40);
+    ledger.apply(depositor, &open_eth, H256::random(), 5, 50);
+    assert_eq!(ledger.history(depositor, EscrowKind::Native).len(), 2);
+    assert_eq!(ledger.history(depositor, EscrowKind::Native)[0].state(), EscrowState::Released);
+    assert_eq!(ledger.current(depositor, EscrowKind::Native).unwrap().transitions()[1].block_number, Some(5));
+    assert_eq!(ledger.current(depositor, EscrowKind::Native).unwrap().funded_at(), Some(50));
+    assert_eq!(ledger.history(depositor, EscrowKind::Native)[0].transitions()[2].timestamp, Some(40));
+    assert_eq!(ledger.status(random_address(), EscrowKind::Native), None);
+
+    // A second initiate while the escrow is open doesn't hide it.
+    ledger.apply(depositor, &open_eth, H256::random(), 6, 60);
+    assert_eq!(ledger.history(depositor, EscrowKind::Native).len(), 2);
+    assert_eq!(ledger.current(depositor, EscrowKind::Native).unwrap().funded_at(), Some(50));
+    assert!(ledger.active().any(|escrow| escrow.funded_at() == Some(50)));
+    ledger.apply(depositor, &release_eth, H256::random(), 7, 70);
+    assert_eq!(ledger.status(depositor, EscrowKind::Native), Some(EscrowState::Released));
+}
+
+#[tokio::test]
+async fn test_escrow_calls_check_state_before_sending() {
+    let manager = mock_reputation_manager();
+    let mut escrow = Escrow::new(random_address(), EscrowAsset::Native { amount: U256::from(1) });
+
+    let result = manager.release(&mut escrow).await;
+    assert!(matches!(result, Err(ReputationError::Escrow(EscrowError::InvalidTransition { .. }))));
+    assert_eq!(escrow.state(), EscrowState::Initiated);
+
+    escrow.transition(EscrowState::Funded, None, None).unwrap();
+    let result = manager.refund(&mut escrow).await;
+    assert!(matches!(result, Err(ReputationError::Contract(_))));
+    assert_eq!(escrow.state(), EscrowState::Funded);
+
+    let result = manager.open_escrow(EscrowAsset::Erc20 { amount: U256::from(10) }).await;
+    assert!(result.unwrap_err().to_string().contains("Mock send_transaction"));
+}
+
+#[tokio::test]
+async fn test_open_escrow_tracks_the_token_id_and_reports_dropped_transactions() {
+    let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = sent.clone();
+    let provider = scripted_provider(move |method, params| {
+        if method == "eth_sendTransaction" {
+            recorded.lock().unwrap().push(call_data(&params));
+        }
+        answer_transaction(method).ok_or_else(|| format!("unexpected {}", method))
+    });
+    let manager = ReputationManager::new(random_address(), Arc::new(provider), create_test_wallet());
+
+    let escrow = manager.open_escrow(EscrowAsset::Erc721 { token_id: Some(U256::from(42)) }).await.unwrap();
+    assert_eq!(escrow.state(), EscrowState::Funded);
+    assert_eq!(escrow.asset, EscrowAsset::Erc721 { token_id: Some(U256::from(42)) });
+    assert_eq!(sent.lock().unwrap().pop().unwrap(), manager.contract.initiate_erc721_escrow().calldata().unwrap());
+
+    // The node forgets the transaction: it was dropped, not mined.
+    let provider = scripted_provider(|method, _| match method {
+        "eth_getTransactionByHash" => Ok(serde_json::Value::Null),
+        _ => answer_transaction(method).ok_or_else(|| format!("unexpected {}", method)),
+    });
+    let manager = ReputationManager::new(random_address(), Arc::new(provider), create_test_wallet());
+    let mut escrow = Escrow::new(manager.wallet.address(), EscrowAsset::Native { amount: U256::one() });
+    escrow.transition(EscrowState::Funded, None, None).unwrap();
+    let result = manager.release(&mut escrow).await;
+    assert!(matches!(result, Err(ReputationError::Dropped(hash)) if hash == H256::repeat_byte(0x11)));
+    assert_eq!(escrow.state(), EscrowState::Funded);
+}
+
+/// A token that answers the view calls in `views`, keyed by signature,
+/// reverts any other call and mines every transaction, recording its input.
+/// Transactions calling one of `reverts` fail gas estimation.
+fn token_provider(
+    views: Vec<(&'static str, ethers::abi::Token)>,
+    reverts: &[&'static str],
+    sent: Arc<std::sync::Mutex<Vec<Bytes>>>,
+) -> Provider<ScriptedClient> {
+    let views: Vec<([u8; 4], Bytes)> = views
+        .into_iter()
+        .map(|(signature, value)| (ethers::utils::id(signature), Bytes::from(ethers::abi::encode(&[value]))))
+        .collect();
+    let reverts: Vec<[u8; 4]> = reverts.iter().map(ethers::utils::id).collect();
+    scripted_provider(move |method, params| {
+        let data = call_data(&params);
+        match method {
+            "eth_estimateGas" if reverts.iter().any(|selector| data.starts_with(selector)) => {
+                Err("execution reverted".to_string())
+            }
+            "eth_call" => views
+                .iter()
+                .find(|(selector, _)| data.starts_with(selector))
+                .map(|(_, value)| serde_json::json!(value))
+                .ok_or_else(|| "execution reverted".to_string()),
+            "eth_sendTransaction" => {
+                sent.lock().unwrap().push(data);
+                Ok(serde_json::json!(H256::repeat_byte(0x11)))
+            }
+            _ => answer_transaction(method).ok_or_else(|| format!("unexpected {}", method)),
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_erc20_allowance_reuses_existing_and_falls_back_to_approve() {
+    use ethers::abi::Token;
+    let (token, spender, amount) = (random_address(), random_address(), U256::from(100));
+    let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    // Enough allowance already: nothing is sent.
+    let views = vec![("balanceOf(address)", Token::Uint(amount)), ("allowance(address,address)", Token::Uint(amount))];
+    let manager = ReputationManager::new(random_address(), Arc::new(token_provider(views, &[], sent.clone())), create_test_wallet());
+    let approval = manager.ensure_erc20_allowance(token, spender, amount, ApprovalStrategy::default()).await.unwrap();
+    assert_eq!(approval, Approval::Existing);
+    assert!(sent.lock().unwrap().is_empty());
+
+    // No allowance and no EIP-2612 support: the permit attempt falls back to approve.
+    let views = vec![("balanceOf(address)", Token::Uint(amount)), ("allowance(address,address)", Token::Uint(U256::zero()))];
+    let manager = ReputationManager::new(random_address(), Arc::new(token_provider(views, &[], sent.clone())), create_test_wallet());
+    let approval = manager.ensure_erc20_allowance(token, spender, amount, ApprovalStrategy::default()).await.unwrap();
+    assert!(matches!(approval, Approval::Approved(_)));
+    let sent = sent.lock().unwrap();
+    assert_eq!(sent.len(), 1);
+    assert!(sent[0].starts_with(&ethers::utils::id("approve(address,uint256)")));
+}
+
+#[tokio::test]
+async fn test_erc20_permit_falls_back_to_approve_when_it_fails() {
+    use ethers::abi::Token;
+    let (token, spender, amount) = (random_address(), random_address(), U256::from(100));
+    let permit = "permit(address,address,uint256,uint256,uint8,bytes32,bytes32)";
+    let views = || {
+        vec![
+            ("balanceOf(address)", Token::Uint(amount)),
+            ("allowance(address,address)", Token::Uint(U256::zero())),
+            ("DOMAIN_SEPARATOR()", Token::FixedBytes(vec![7u8; 32])),
+            ("nonces(address)", Token::Uint(U256::zero())),
+        ]
+    };
+
+    // The token has a domain separator but its permit reverts, like DAI's.
+    let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let manager = ReputationManager::new(random_address(), Arc::new(token_provider(views(), &[permit], sent.clone())), create_test_wallet());
+    let approval = manager.ensure_erc20_allowance(token, spender, amount, ApprovalStrategy::default()).await.unwrap();
+    assert!(matches!(approval, Approval::Approved(_)));
+    let calls = sent.lock().unwrap().clone();
+    assert_eq!(calls.len(), 1);
+    assert!(calls[0].starts_with(&ethers::utils::id("approve(address,uint256)")));
+
+    // The permit is mined but the allowance is still missing.
+    let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let manager = ReputationManager::new(random_address(), Arc::new(token_provider(views(), &[], sent.clone())), create_test_wallet());
+    let approval = manager.ensure_erc20_allowance(token, spender, amount, ApprovalStrategy::default()).await.unwrap();
+    assert!(matches!(approval, Approval::Approved(_)));
+    let calls = sent.lock().unwrap().clone();
+    assert_eq!(calls.len(), 2);
+    assert!(calls[0].starts_with(&ethers::utils::id(permit)));
+    assert!(calls[1].starts_with(&ethers::utils::id("approve(address,uint256)")));
+}
+
+#[tokio::test]
+async fn test_erc721_approval_reuses_existing() {
+    use ethers::abi::Token;
+    let (token, spender, token_id) = (random_address(), random_address(), U256::from(7));
+    let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let wallet = create_test_wallet();
+
+    let views = vec![
+        ("ownerOf(uint256)", Token::Address(wallet.address())),
+        ("isApprovedForAll(address,address)", Token::Bool(false)),
+        ("getApproved(uint256)", Token::Address(spender)),
+    ];
+    let manager = ReputationManager::new(random_address(), Arc::new(token_provider(views, &[], sent.clone())), wallet);
+    let approval = manager.ensure_erc721_approval(token, spender, token_id, NftApprovalScope::Token).await.unwrap();
+    assert_eq!(approval, Approval::Existing);
+    assert!(sent.lock().unwrap().is_empty());
+
+    let views = vec![("ownerOf(uint256)", Token::Address(random_address()))];
+    let manager = ReputationManager::new(random_address(), Arc::new(token_provider(views, &[], sent.clone())), create_test_wallet());
+    let result = manager.ensure_erc721_approval(token, spender, token_id, NftApprovalScope::All).await;
+    assert!(matches!(result, Err(ReputationError::NotTokenOwner { .. })));
+}
+
+#[test]
+fn test_permit_signatures_recover_to_owner() {
+    assert_eq!(
+        hex::encode(permit_typehash()),
+        "6e71edae12b1b97f4d1f60370fef10105fa2faae0126114a169c64845d6126c9"
+    );
+
+    let wallet = create_test_wallet();
+    let (domain, spender, value) = ([5u8; 32], random_address(), U256::from(1_000));
+    let permit = sign_permit(&wallet, domain, spender, value, U256::zero(), U256::from(1_700_000_000u64)).unwrap();
+    assert!(permit.v == 27 || permit.v == 28);
+
+    let digest = permit_digest(domain, wallet.address(), spender, value, U256::zero(), permit.deadline);
+    let signature = Signature { r: U256::from_big_endian(&permit.r), s: U256::from_big_endian(&permit.s), v: permit.v as u64 };
+    assert_eq!(signature.recover(digest).unwrap(), wallet.address());
+
+    // The nonce and domain are bound into the digest.
+    assert_ne!(digest, permit_digest(domain, wallet.address(), spender, value, U256::one(), permit.deadline));
+    assert_ne!(digest, permit_digest([6u8; 32], wallet.address(), spender, value, U256::zero(), permit.deadline));
+}
+
+#[tokio::test]
+async fn test_token_approvals_surface_contract_errors() {
+    let manager = mock_reputation_manager();
+    let (token, spender) = (random_address(), random_address());
+
+    assert_eq!(
+        ApprovalStrategy::default(),
+        ApprovalStrategy::PreferPermit { valid_for: DEFAULT_PERMIT_VALIDITY }
+    );
+    let result = manager.open_erc20_escrow(token, spender, U256::from(10), ApprovalStrategy::default()).await;
+    assert!(matches!(result, Err(ReputationError::Contract(_))));
+    assert!(result.unwrap_err().to_string().contains("Mock call"));
+
+    let result = manager.open_erc721_escrow(token, spender, U256::from(1), NftApprovalScope::All).await;
+    assert!(matches!(result, Err(ReputationError::Contract(_))));
+}
+
+#[test]
+fn test_escrow_watcher_tracks_wallet_escrows() {
+    let manager = Arc::new(mock_reputation_manager());
+    let wallet = manager.wallet.address();
+    let mut watcher = EscrowWatcher::new(Arc::clone(&manager), 0).with_timeout(Duration::from_secs(100));
+    assert_eq!(EscrowWatcher::new(Arc::clone(&manager), 0).timeout(), DEFAULT_ESCROW_TIMEOUT);
+
+    let mut ledger = EscrowLedger::new();
+    let eth = EscrowCall::Initiate(EscrowAsset::Native { amount: U256::from(5) });
+    ledger.apply(wallet, &eth, H256::random(), 1, 900);
+    ledger.apply(random_address(), &eth, H256::random(), 1, 900);
+    ledger.apply(wallet, &EscrowCall::Initiate(EscrowAsset::Erc20 { amount: U256::from(7) }), H256::random(), 2, 950);
+
+    // Only the wallet's escrows are tracked, once each, due `timeout` after
+    // the block that funded them rather than when they were first seen.
+    let notifications = watcher.observe(&ledger, 1_000);
+    assert_eq!(notifications.len(), 2);
+    assert!(matches!(notifications[0], EscrowNotification::Tracked { deadline: 1_000, .. }));
+    assert!(matches!(notifications[1], EscrowNotification::Tracked { deadline: 1_050, .. }));
+    assert!(watcher.observe(&ledger, 1_050).is_empty());
+    assert!(watcher.due(999).is_empty());
+    assert_eq!(watcher.due(1_000).len(), 1);
+    assert_eq!(watcher.due(1_050).len(), 2);
+
+    // A release seen on chain closes the escrow and stops tracking it.
+    ledger.apply(wallet, &EscrowCall::Release(EscrowKind::Native), H256::random(), 3, 1_010);
+    let notifications = watcher.observe(&ledger, 1_060);
+    assert!(matches!(
+        &notifications[..],
+        [EscrowNotification::Transition { escrow, from: EscrowState::Funded, automatic: false }]
+            if escrow.state() == EscrowState::Released
+    ));
+    let kinds: Vec<EscrowKind> = watcher.watched().map(|(escrow, _)| escrow.kind()).collect();
+    assert_eq!(kinds, [EscrowKind::Erc20]);
+
+    // A new escrow of the same kind gets its own deadline.
+    ledger.apply(wallet, &eth, H256::random(), 4, 1_065);
+    let notifications = watcher.observe(&ledger, 1_070);
+    assert!(matches!(&notifications[..], [EscrowNotification::Tracked { deadline: 1_165, .. }]));
+
+    // Escrows of other depositors can't be tracked.
+    let mut watcher = EscrowWatcher::new(Arc::clone(&manager), 0);
+    let mut foreign = Escrow::new(random_address(), EscrowAsset::Native { amount: U256::one() });
+    foreign.transition(EscrowState::Funded, None, None).unwrap();
+    watcher.track(foreign);
+    let mut own = Escrow::new(wallet, EscrowAsset::Erc721 { token_id: None });
+    own.transition(EscrowState::Funded, Some(H256::random()), Some(1)).unwrap();
+    watcher.track_until(own, 10);
+    assert_eq!(watcher.watched().map(|(_, deadline)| deadline).collect::<Vec<_>>(), [10]);
+}
+
+#[tokio::test]
+async fn test_escrow_watcher_skips_refunds_when_sync_fails() {
+    let manager = Arc::new(mock_reputation_manager());
+    let mut watcher = EscrowWatcher::new(Arc::clone(&manager), 0);
+    let mut escrow = Escrow::new(manager.wallet.address(), EscrowAsset::Native { amount: U256::one() });
+    escrow.transition(EscrowState::Funded, Some(H256::random()), Some(1)).unwrap();
+    watcher.track_until(escrow, 0);
+
+    let result = watcher.run_once(100).await;
+    assert!(result.unwrap_err().to_string().contains("Mock get_block_number"));
+    assert_eq!(watcher.watched().next().unwrap().0.state(), EscrowState::Funded);
+}
+
+#[tokio::test]
+async fn test_escrow_watcher_syncs_within_its_block_budget() {
+    let fetched = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = fetched.clone();
+    let provider = scripted_provider(move |method, params| match method {
+        "eth_blockNumber" => Ok(serde_json::json!(U64::from(9))),
+        "eth_getBlockByNumber" => {
+            let number: U64 = serde_json::from_value(params[0].clone()).unwrap();
+            recorded.lock().unwrap().push(number.as_u64());
+            let block = Block::<Transaction> { number: Some(number), timestamp: U256::from(100), ..Default::default() };
+            Ok(serde_json::json!(block))
+        }
+        _ => Err(format!("unexpected {}", method)),
+    });
+    let manager = Arc::new(ReputationManager::new(random_address(), Arc::new(provider), create_test_wallet()));
+    let mut watcher = EscrowWatcher::new(Arc::clone(&manager), 0).with_block_budget(4);
+    let mut escrow = Escrow::new(manager.wallet.address(), EscrowAsset::Native { amount: U256::one() });
+    escrow.transition(EscrowState::Funded, Some(H256::random()), Some(1)).unwrap();
+    watcher.track_until(escrow, 0);
+
+    // Blocks 0..=9 take three passes; the overdue escrow waits until the
+    // watcher has caught up, so no refund is attempted before then.
+    for expected in [4, 8] {
+        assert!(watcher.run_once(1_000).await.unwrap().is_empty());
+        assert_eq!(fetched.lock().unwrap().len(), expected);
+    }
+    let notifications = watcher.run_once(1_000).await.unwrap();
+    assert_eq!(fetched.lock().unwrap().clone(), (0..10).collect::<Vec<u64>>());
+    // Caught up: the refund is attempted, and fails on this node.
+    assert!(matches!(&notifications[..], [EscrowNotification::RefundFailed { .. }]));
+}
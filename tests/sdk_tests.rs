@@ -1,7 +1,6 @@
 // tests/sdk_tests.rs
 
 use swtch_sdk::SwtchSDK;
-use ethers::types::{H160, U256};
 
 #[test]
 fn test_add_configuration() {
@@ -52,4 +51,26 @@ fn test_use_nonexistent_configuration() {
     let mut sdk = SwtchSDK::new();
     let result = sdk.use_configuration("nonexistent_config");
     assert!(result.is_err());
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_network_calls_require_initialization() {
+    let sdk = SwtchSDK::new();
+    assert!(sdk.network_manager().is_none());
+    let result = sdk.get_network_service("0x1234567890123456789012345678901234567890").await;
+    assert_eq!(result.unwrap_err().to_string(), "NetworkManager not initialized");
+    assert!(sdk.get_service_providers_page(0, 10).await.is_err());
+}
+
+#[tokio::test]
+async fn test_initialize_network_manager() {
+    let mut sdk = SwtchSDK::new();
+    let address = "0x1234567890123456789012345678901234567890";
+    assert!(sdk.initialize_network_manager(address).await.is_err());
+
+    sdk.add_configuration("local", "ethereum", "mainnet", "http://localhost:8545", "your_public_key", "your_private_key").unwrap();
+    sdk.use_configuration("local").unwrap();
+    assert!(sdk.initialize_network_manager("not an address").await.is_err());
+    sdk.initialize_network_manager(address).await.unwrap();
+    assert_eq!(sdk.network_manager().unwrap().address(), address.parse().unwrap());
+}
@@ -0,0 +1,425 @@
+// tests/secrets_tests.rs
+
+use swtch_sdk::secrets::{
+    BackupArchive, BackupEntry, BackupError, BackupManifest, CacheError, CacheKey, CachedSecretsSpace, ExpiringSecret,
+    ExpiryLedger, ExpiryScheduler, IndexedEvent, Inheritance, ReadSource, SecretCache, SecretsError, SecretsManager,
+    SecretsSpace, SpaceEvent, SpaceIndex, SpaceIndexer, SpaceNode, VersionHead, VersionedIdentifier,
+};
+use ethers::prelude::*;
+use ethers::types::{Bytes, H256, U256};
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+use common::{create_test_wallet, mock_provider, random_address, temp_dir};
+
+#[test]
+fn test_versioned_identifier_roundtrip() {
+    let identifier = VersionedIdentifier::new("db-password", 7);
+    let bytes = identifier.to_bytes();
+    assert_eq!(bytes.as_ref(), b"db-password#v7");
+    assert_eq!(VersionedIdentifier::parse(&bytes), Some(identifier));
+
+    assert_eq!(VersionedIdentifier::parse(b"db-password"), None);
+    assert_eq!(VersionedIdentifier::parse(b"db-password#head"), None);
+    assert_eq!(VersionedIdentifier::parse(b"db-password#v"), None);
+}
+
+#[test]
+fn test_version_head_encoding() {
+    let head = VersionHead { latest: 5, oldest: 3, delegates: vec![random_address(), random_address()] };
+    assert_eq!(VersionHead::decode(&head.encode()), Some(head.clone()));
+    assert_eq!(head.retained().collect::<Vec<_>>(), vec![3, 4, 5]);
+
+    // Records written before delegates were tracked decode with none.
+    let legacy = [5u32.to_be_bytes(), 3u32.to_be_bytes()].concat();
+    assert_eq!(VersionHead::decode(&legacy), Some(VersionHead { latest: 5, oldest: 3, delegates: Vec::new() }));
+
+    // oldest must be within 1..=latest
+    let invalid = VersionHead { latest: 2, oldest: 3, delegates: Vec::new() };
+    assert_eq!(VersionHead::decode(&invalid.encode()), None);
+    assert_eq!(VersionHead::decode(&[0u8; 4]), None);
+    assert_eq!(VersionHead::decode(&head.encode()[..20]), None);
+}
+
+#[tokio::test]
+async fn test_get_latest_secret_surfaces_provider_errors() {
+    let space = SecretsSpace::new(random_address(), Arc::new(mock_provider()));
+
+    let result = space.get_latest_secret("db-password").await;
+    match result {
+        Err(SecretsError::Contract(e)) => assert!(e.to_string().contains("Mock call")),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_add_secret_paid_requires_fee_quote() {
+    let space = SecretsSpace::new(random_address(), Arc::new(mock_provider()));
+
+    let result = space
+        .add_secret_paid(Bytes::from_static(b"api-key"), Bytes::from_static(b"value"), Some(U256::from(10)))
+        .await;
+    match result {
+        Err(SecretsError::Contract(e)) => assert!(e.to_string().contains("Mock call")),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_fee_error_messages() {
+    let error: SecretsError<common::CustomMockProvider> = SecretsError::FeeExceedsMax {
+        fee: U256::from(20),
+        max_fee: U256::from(10),
+    };
+    assert_eq!(error.to_string(), "space fee 20 exceeds maximum 10");
+
+    let error: SecretsError<common::CustomMockProvider> = SecretsError::InsufficientFees {
+        requested: U256::from(5),
+        collected: U256::from(1),
+    };
+    assert_eq!(error.to_string(), "requested 5 but only 1 fees collected");
+}
+
+fn indexed(block_number: u64, event: SpaceEvent) -> IndexedEvent {
+    IndexedEvent {
+        block_number,
+        transaction_hash: H256::random(),
+        log_index: U256::zero(),
+        event,
+    }
+}
+
+#[test]
+fn test_space_index_tracks_identifiers_and_grants() {
+    let alice = random_address();
+    let bob = random_address();
+    let api_key = Bytes::from_static(b"api-key");
+    let db_password = Bytes::from_static(b"db-password");
+
+    let mut index = SpaceIndex::new();
+    for event in [
+        indexed(10, SpaceEvent::SecretAdded { identifier: api_key.clone() }),
+        indexed(11, SpaceEvent::SecretAdded { identifier: db_password.clone() }),
+        indexed(12, SpaceEvent::DelegateAuthorized { delegate: alice, identifier: api_key.clone() }),
+        indexed(12, SpaceEvent::DelegateAuthorized { delegate: bob, identifier: api_key.clone() }),
+        indexed(13, SpaceEvent::DelegateAuthorized { delegate: alice, identifier: db_password.clone() }),
+        indexed(14, SpaceEvent::DelegateRevoked { delegate: bob, identifier: api_key.clone() }),
+        indexed(15, SpaceEvent::SecretDeleted { identifier: db_password.clone() }),
+        indexed(16, SpaceEvent::FeeAdjusted { fee: U256::from(42) }),
+    ] {
+        index.apply(&event);
+    }
+
+    assert_eq!(index.identifiers().cloned().collect::<Vec<_>>(), vec![api_key.clone()]);
+    assert_eq!(index.deleted().cloned().collect::<Vec<_>>(), vec![db_password.clone()]);
+    assert_eq!(index.delegates(&api_key), vec![alice]);
+    assert!(index.delegates(&db_password).is_empty());
+    assert_eq!(index.grants_for(alice), vec![api_key]);
+    assert!(index.grants_for(bob).is_empty());
+    assert_eq!(index.fee(), Some(U256::from(42)));
+    assert_eq!(index.last_block(), Some(16));
+
+    index.apply(&indexed(17, SpaceEvent::SecretAdded { identifier: db_password.clone() }));
+    assert!(index.contains(&db_password));
+    assert_eq!(index.deleted().count(), 0);
+}
+
+#[tokio::test]
+async fn test_space_indexer_sync_surfaces_provider_errors() {
+    let space = SecretsSpace::new(random_address(), Arc::new(mock_provider()));
+    let mut indexer = SpaceIndexer::new(space, 0);
+
+    let result = indexer.sync().await;
+    assert!(result.unwrap_err().to_string().contains("Mock get_block_number"));
+    assert_eq!(indexer.index().last_block(), None);
+}
+
+#[test]
+fn test_secret_cache_roundtrip_and_invalidation() {
+    let dir = temp_dir("cache");
+    let (secret_key, _) = ecies::utils::generate_keypair();
+    let cache = SecretCache::new(&dir, Duration::from_secs(60), secret_key);
+    let space = random_address();
+    let key = CacheKey::new(space, Bytes::from_static(b"api-key"));
+    let value = Bytes::from_static(b"s3cr3t");
+
+    assert_eq!(cache.get(&key).unwrap(), None);
+    cache.put(&key, &value).unwrap();
+    let cached = cache.get(&key).unwrap().unwrap();
+    assert_eq!(cached.value, value);
+    assert!(cached.fresh);
+
+    // Entries are sealed: neither the value nor the identifier is on disk in the clear.
+    let space_dir = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+    let entry = std::fs::read_dir(space_dir).unwrap().next().unwrap().unwrap().path();
+    let raw = std::fs::read(&entry).unwrap();
+    assert!(!raw.windows(value.len()).any(|w| w == value.as_ref()));
+    // File names are keyed, so they can't be matched against a guessed identifier.
+    let unkeyed = format!("{}.cache", hex::encode(ethers::utils::keccak256(&key.identifier)));
+    assert_ne!(entry.file_name().unwrap().to_str().unwrap(), unkeyed);
+
+    // A different key can't find the entry, let alone read it.
+    let (other_key, _) = ecies::utils::generate_keypair();
+    let other = SecretCache::new(&dir, Duration::from_secs(60), other_key);
+    assert_eq!(other.get(&key).unwrap(), None);
+
+    // An entry that doesn't decrypt is reported as such.
+    std::fs::write(&entry, b"garbage").unwrap();
+    assert!(matches!(cache.get(&key), Err(CacheError::Crypto(_))));
+    cache.put(&key, &value).unwrap();
+
+    cache
+        .apply_events(space, &[indexed(1, SpaceEvent::SecretDeleted { identifier: key.identifier.clone() })])
+        .unwrap();
+    assert_eq!(cache.get(&key).unwrap(), None);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_cached_space_serves_stale_entries_offline() {
+    let dir = temp_dir("offline");
+    let (secret_key, _) = ecies::utils::generate_keypair();
+    let space = SecretsSpace::new(random_address(), Arc::new(mock_provider()));
+    let key = CacheKey::new(space.address(), Bytes::from_static(b"api-key"));
+
+    // A zero TTL makes every entry stale, forcing an RPC attempt.
+    let cache = SecretCache::new(&dir, Duration::ZERO, secret_key);
+    cache.put(&key, &Bytes::from_static(b"s3cr3t")).unwrap();
+
+    let cached = CachedSecretsSpace::new(space, cache);
+    assert!(cached.get_secret(key.identifier.clone()).await.is_err());
+
+    let cached = cached.with_offline_fallback(true);
+    let (value, source) = cached.get_secret(key.identifier.clone()).await.unwrap();
+    assert_eq!(value.as_ref(), b"s3cr3t");
+    assert_eq!(source, ReadSource::Offline);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_cached_space_treats_unreadable_entries_as_misses() {
+    let dir = temp_dir("corrupt");
+    let (secret_key, _) = ecies::utils::generate_keypair();
+    let space = SecretsSpace::new(random_address(), Arc::new(mock_provider()));
+    let key = CacheKey::new(space.address(), Bytes::from_static(b"api-key"));
+    let cache = SecretCache::new(&dir, Duration::from_secs(60), secret_key);
+    cache.put(&key, &Bytes::from_static(b"s3cr3t")).unwrap();
+
+    let space_dir = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+    let entry = std::fs::read_dir(&space_dir).unwrap().next().unwrap().unwrap().path();
+    std::fs::write(&entry, b"garbage").unwrap();
+
+    // The read goes to the network instead of failing on the cache, and the bad entry is dropped.
+    let cached = CachedSecretsSpace::new(space, cache).with_offline_fallback(true);
+    match cached.get_secret(key.identifier.clone()).await {
+        Err(SecretsError::Contract(e)) => assert!(e.to_string().contains("Mock call")),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+    assert!(!entry.exists());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+fn sample_manifest(delegate: Address) -> BackupManifest {
+    BackupManifest {
+        format_version: 1,
+        source_space: random_address(),
+        source_chain_id: U256::from(11155111u64),
+        created_at: 1_700_000_000,
+        entries: vec![
+            BackupEntry {
+                identifier: Bytes::from_static(b"api-key"),
+                value: Bytes::from_static(b"s3cr3t"),
+                delegates: vec![delegate],
+            },
+            BackupEntry {
+                identifier: Bytes::from_static(b"db-password"),
+                value: Bytes::from_static(b"hunter2"),
+                delegates: vec![],
+            },
+        ],
+    }
+}
+
+#[tokio::test]
+async fn test_backup_archive_seal_and_open() {
+    let manifest = sample_manifest(random_address());
+    let (secret_key, public_key) = ecies::utils::generate_keypair();
+    let wallet = create_test_wallet();
+
+    let archive = manifest.seal(&public_key, &wallet).await.unwrap();
+    let archive = BackupArchive::from_json(&archive.to_json().unwrap()).unwrap();
+    assert_eq!(archive.signer, wallet.address());
+    assert_eq!(archive.open(&secret_key, wallet.address()).unwrap(), manifest);
+
+    // Signed by someone else
+    assert!(matches!(archive.open(&secret_key, random_address()), Err(BackupError::Signature(_))));
+
+    // Tampered ciphertext
+    let mut tampered = archive.clone();
+    let mut sealed = tampered.sealed.to_vec();
+    sealed[70] ^= 0x01;
+    tampered.sealed = Bytes::from(sealed);
+    assert!(matches!(tampered.open(&secret_key, wallet.address()), Err(BackupError::Signature(_))));
+
+    // Rewritten and re-signed by an attacker who names themselves as the signer
+    let mut forged_manifest = manifest.clone();
+    forged_manifest.entries[0].value = Bytes::from_static(b"attacker-value");
+    let attacker = create_test_wallet();
+    let forged = forged_manifest.seal(&public_key, &attacker).await.unwrap();
+    assert_eq!(forged.signer, attacker.address());
+    assert!(matches!(forged.open(&secret_key, wallet.address()), Err(BackupError::Signature(_))));
+}
+
+#[tokio::test]
+async fn test_import_backup_dry_run_into_empty_space() {
+    let delegate = random_address();
+    let manifest = sample_manifest(delegate);
+    let space = SecretsSpace::new(random_address(), Arc::new(mock_provider()));
+
+    let report = space.import_backup(&manifest, &SpaceIndex::new(), true, None).await.unwrap();
+    assert!(report.dry_run);
+    assert!(report.receipts.is_empty());
+    assert_eq!(
+        report.diff.added,
+        vec![Bytes::from_static(b"api-key"), Bytes::from_static(b"db-password")]
+    );
+    assert_eq!(report.diff.grants, vec![(delegate, Bytes::from_static(b"api-key"))]);
+    assert!(report.diff.changed.is_empty());
+    assert!(report.diff.untracked.is_empty());
+}
+
+#[test]
+fn test_expiring_secret_encoding() {
+    let secret = ExpiringSecret::new(Bytes::from_static(b"s3cr3t"), 1_000);
+    let decoded = ExpiringSecret::decode(&secret.encode());
+    assert_eq!(decoded, secret);
+    assert!(!decoded.is_expired(999));
+    assert!(decoded.is_expired(1_000));
+
+    // Values written without a TTL never expire.
+    let plain = ExpiringSecret::decode(b"s3cr3t");
+    assert_eq!(plain.expires_at, None);
+    assert!(!plain.is_expired(u64::MAX));
+
+    // Plain values that merely look like an envelope are returned untouched.
+    let lookalike = b"SWX\x02\x00\x00\x00\x00\x00\x00\x03\xe8\x00\x00\x00\x06s3cr3t12345678";
+    assert_eq!(ExpiringSecret::decode(lookalike), ExpiringSecret { value: Bytes::from_static(lookalike), expires_at: None });
+    let mut truncated = secret.encode().to_vec();
+    truncated.pop();
+    assert_eq!(ExpiringSecret::decode(&truncated).expires_at, None);
+}
+
+#[test]
+fn test_expiry_ledger_tracks_due_entries() {
+    let delegate = random_address();
+    let api_key = Bytes::from_static(b"api-key");
+    let mut ledger = ExpiryLedger::new();
+    ledger.record_grant(delegate, api_key.clone(), 100);
+    ledger.record_grant(delegate, api_key.clone(), 200);
+    ledger.record_secret(api_key.clone(), 300);
+
+    assert_eq!(ledger.grants.len(), 1);
+    assert!(ledger.is_grant_active(delegate, &api_key, 199));
+    assert!(!ledger.is_grant_active(delegate, &api_key, 200));
+    assert!(ledger.is_grant_active(random_address(), &api_key, 1_000));
+    assert!(ledger.due_grants(199).is_empty());
+    assert_eq!(ledger.due_grants(200).len(), 1);
+    assert_eq!(ledger.due_secrets(300).len(), 1);
+
+    let dir = temp_dir("ledger");
+    let path = dir.join("expiry.json");
+    ledger.save(&path).unwrap();
+    assert_eq!(ExpiryLedger::load(&path).unwrap(), ledger);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_expiry_scheduler_keeps_failed_entries() {
+    let mut ledger = ExpiryLedger::new();
+    ledger.record_grant(random_address(), Bytes::from_static(b"api-key"), 100);
+    ledger.record_secret(Bytes::from_static(b"api-key"), 100);
+    let ledger = Arc::new(tokio::sync::Mutex::new(ledger));
+
+    let space = SecretsSpace::new(random_address(), Arc::new(mock_provider()));
+    let scheduler = ExpiryScheduler::new(space, Arc::clone(&ledger));
+    let report = scheduler.run_once(100).await;
+
+    assert!(report.revoked.is_empty());
+    assert!(report.deleted.is_empty());
+    assert_eq!(report.failed.len(), 2);
+    let ledger = ledger.lock().await;
+    assert_eq!(ledger.grants.len(), 1);
+    assert_eq!(ledger.secrets.len(), 1);
+}
+
+fn space_node(did: Address, disabled: bool, children: Vec<SpaceNode>) -> SpaceNode {
+    SpaceNode {
+        did,
+        space: if disabled { Address::zero() } else { random_address() },
+        disabled,
+        fee: if disabled { None } else { Some(U256::from(1)) },
+        children,
+    }
+}
+
+#[test]
+fn test_space_tree_navigation_and_report() {
+    let (root, team, alice, bob) = (random_address(), random_address(), random_address(), random_address());
+    let tree = space_node(
+        root,
+        false,
+        vec![
+            space_node(team, false, vec![space_node(alice, false, vec![])]),
+            space_node(bob, true, vec![]),
+        ],
+    );
+
+    assert_eq!(tree.find(alice).unwrap().did, alice);
+    assert!(tree.find(random_address()).is_none());
+    let path: Vec<Address> = tree.path_to(alice).unwrap().iter().map(|node| node.did).collect();
+    assert_eq!(path, vec![root, team, alice]);
+
+    let report = tree.report();
+    let summary: Vec<(Address, Option<Address>, usize, bool)> = report
+        .iter()
+        .map(|status| (status.did, status.parent, status.depth, status.disabled))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (root, None, 0, false),
+            (team, Some(root), 1, false),
+            (alice, Some(team), 2, false),
+            (bob, Some(root), 1, true),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_space_tree_surfaces_provider_errors() {
+    let manager = SecretsManager::new(random_address(), Arc::new(mock_provider()));
+    let result = manager.space_tree(random_address(), 3).await;
+    assert!(result.unwrap_err().to_string().contains("Mock call"));
+}
+
+#[tokio::test]
+async fn test_find_secret_skips_disabled_spaces() {
+    let manager = SecretsManager::new(random_address(), Arc::new(mock_provider()));
+    let (root, child) = (random_address(), random_address());
+    let tree = space_node(root, true, vec![space_node(child, true, vec![])]);
+
+    let found = manager
+        .find_secret(&tree, child, Bytes::from_static(b"api-key"), Inheritance::Ancestors)
+        .await
+        .unwrap();
+    assert_eq!(found, None);
+}